@@ -0,0 +1,34 @@
+//! Runs the input as a program on the interpreter and on the reference model from the tests.
+//! The first byte picks the quirks, the next 16 are V0 to VF and the next 2 are I.
+
+#![no_main]
+
+#[path = "../../tests/reference/mod.rs"]
+mod reference;
+
+use chip8::Quirks;
+use libfuzzer_sys::fuzz_target;
+
+const STEPS: usize = 1000;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[config], rest)) = data.split_first_chunk::<1>() else {
+        return;
+    };
+    let Some((registers, rest)) = rest.split_first_chunk::<16>() else {
+        return;
+    };
+    let Some((index, program)) = rest.split_first_chunk::<2>() else {
+        return;
+    };
+
+    let (_, quirks) = Quirks::PRESETS[config as usize % Quirks::PRESETS.len()];
+    let quirks = Quirks {
+        index_overflow: config & 0x80 != 0,
+        ..quirks
+    };
+    let index = u16::from_be_bytes(*index);
+    if let Err(diff) = reference::compare(program, quirks, *registers, index, STEPS) {
+        panic!("{quirks:?}\n{diff}");
+    }
+});
@@ -0,0 +1,22 @@
+//! Runs the input as a ROM for a few frames, which may fail with an `ExeError` but not panic.
+
+#![no_main]
+
+use chip8::{Interpreter, Quirks, OFFSET};
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 10;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&config, rom)) = data.split_first() else {
+        return;
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.quirks = Quirks::PRESETS[config as usize % Quirks::PRESETS.len()].1;
+    interpreter.load_rom(&rom[..rom.len().min(4096 - OFFSET)]);
+    for _ in 0..FRAMES {
+        if interpreter.update(&[], &mut []).is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,406 @@
+//! The interpreter as a libretro core: the screen as video, the buzzer as audio, the keyboard
+//! and joypad as the keypad, and save states.
+
+use std::ffi::{c_char, c_uint, c_void, CString};
+use std::sync::{Mutex, MutexGuard};
+
+use chip8::record::{self, SAMPLE_RATE};
+use chip8::{get_key, Interpreter, KeypadKey, HEIGHT, OFFSET, STACK_SIZE, TARGET_FPS, WIDTH};
+
+pub mod ffi;
+
+use ffi::*;
+
+/// Audio frames sent with every video frame.
+pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as u64 / TARGET_FPS) as usize;
+// the keyboard keys of the keypad, in the layout of `get_key`
+const KEYBOARD: &str = "1234qwerasdfzxcv";
+// for games that move with 2, 4, 6 and 8
+const JOYPAD: [(c_uint, KeypadKey); 5] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, KeypadKey::Key2),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, KeypadKey::Key8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, KeypadKey::Key4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, KeypadKey::Key6),
+    (RETRO_DEVICE_ID_JOYPAD_A, KeypadKey::Key5),
+];
+
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample: Option<AudioSampleFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+    // from the frontend if it has a log, errors aren't reported otherwise
+    log: Option<LogPrintfFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    log: None,
+});
+
+struct Core {
+    interpreter: Interpreter,
+    rom: Vec<u8>,
+    down: [bool; 16],
+    // sample number, which keeps the buzzer's phase across frames
+    sample: u64,
+    // an instruction failed and the game is stopped
+    failed: bool,
+}
+
+impl Core {
+    fn new(rom: Vec<u8>) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.load_rom(&rom);
+        Self {
+            interpreter,
+            rom,
+            down: [false; 16],
+            sample: 0,
+            failed: false,
+        }
+    }
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+// the libretro API is called from one thread, the locks only make the statics safe
+fn core() -> MutexGuard<'static, Option<Core>> {
+    CORE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn callbacks() -> MutexGuard<'static, Callbacks> {
+    CALLBACKS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    callbacks().environment = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    callbacks().video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(callback: AudioSampleFn) {
+    callbacks().audio_sample = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    callbacks().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    callbacks().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    callbacks().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// # Safety
+///
+/// `info` must point to a `SystemInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    info.write(SystemInfo {
+        library_name: c"CHIP-8".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"ch8|c8".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    });
+}
+
+/// # Safety
+///
+/// `info` must point to a `SystemAvInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    info.write(SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: WIDTH as c_uint,
+            base_height: HEIGHT as c_uint,
+            max_width: WIDTH as c_uint,
+            max_height: HEIGHT as c_uint,
+            aspect_ratio: WIDTH as f32 / HEIGHT as f32,
+        },
+        timing: SystemTiming {
+            fps: TARGET_FPS as f64,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    });
+}
+
+/// # Safety
+///
+/// `game` must be null or point to a `GameInfo` whose `data` holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() || game.size > Interpreter::new().memory.len() - OFFSET {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+
+    let environment = callbacks().environment;
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    let format_set = environment.is_some_and(|environment| {
+        environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        )
+    });
+    if !format_set {
+        return false;
+    }
+    let mut log = LogCallback { log: None };
+    let log_set = environment.is_some_and(|environment| {
+        environment(
+            RETRO_ENVIRONMENT_GET_LOG_INTERFACE,
+            &mut log as *mut LogCallback as *mut c_void,
+        )
+    });
+    callbacks().log = if log_set { log.log } else { None };
+
+    *core() = Some(Core::new(rom));
+    true
+}
+
+/// Loading several games at once isn't supported, this always fails.
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = core().as_mut() {
+        let fresh = Core::new(std::mem::take(&mut core.rom));
+        // through a state, so that the memory stays where `retro_get_memory_data` said
+        let state = fresh.interpreter.save_state();
+        core.interpreter
+            .load_state(&state)
+            .expect("a fresh state loads");
+        core.rom = fresh.rom;
+        core.down = fresh.down;
+        core.sample = fresh.sample;
+        core.failed = fresh.failed;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = *callbacks();
+    if let Some(input_poll) = callbacks.input_poll {
+        input_poll();
+    }
+
+    let (video, audio, error) = {
+        let mut core = core();
+        let Some(core) = core.as_mut() else {
+            return;
+        };
+
+        let down = keypad(callbacks.input_state);
+        for (digit, &down) in down.iter().enumerate() {
+            let Some(key) = KeypadKey::from_digit(digit as u8) else {
+                continue;
+            };
+            match (core.down[digit], down) {
+                (false, true) => core.interpreter.press_key(key),
+                (true, false) => core.interpreter.release_key(key),
+                _ => (),
+            }
+        }
+        core.down = down;
+
+        // only the first error is reported, the game stops there
+        let mut error = None;
+        if !core.failed {
+            if let Err(err) = core.interpreter.update(&[], &mut []) {
+                error = Some(err.to_string());
+                core.failed = true;
+            }
+        }
+
+        let mut rgba = vec![0; WIDTH * HEIGHT * 4];
+        core.interpreter.draw(&mut rgba);
+        let video: Vec<u32> = rgba
+            .chunks_exact(4)
+            .map(|pixel| u32::from_be_bytes([0, pixel[0], pixel[1], pixel[2]]))
+            .collect();
+
+        let on = core.interpreter.buzzing();
+        let mut audio = Vec::with_capacity(SAMPLES_PER_FRAME * 2);
+        for sample in core.sample..core.sample + SAMPLES_PER_FRAME as u64 {
+            let value = if on { record::buzzer(sample) } else { 0 };
+            audio.extend_from_slice(&[value, value]);
+        }
+        core.sample += SAMPLES_PER_FRAME as u64;
+        (video, audio, error)
+    };
+
+    // the core isn't locked while the frontend has control
+    if let (Some(log), Some(error)) = (callbacks.log, error) {
+        let message = CString::new(error).unwrap_or_default();
+        unsafe { log(RETRO_LOG_ERROR, c"%s\n".as_ptr(), message.as_ptr()) };
+    }
+    if let Some(video_refresh) = callbacks.video_refresh {
+        video_refresh(
+            video.as_ptr() as *const c_void,
+            WIDTH as c_uint,
+            HEIGHT as c_uint,
+            WIDTH * 4,
+        );
+    }
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        let mut rest = &audio[..];
+        // the frontend may take fewer frames than it's given
+        while !rest.is_empty() {
+            let taken = audio_sample_batch(rest.as_ptr(), rest.len() / 2);
+            if taken == 0 {
+                break;
+            }
+            rest = &rest[(taken * 2).min(rest.len())..];
+        }
+    } else if let Some(audio_sample) = callbacks.audio_sample {
+        for frame in audio.chunks_exact(2) {
+            audio_sample(frame[0], frame[1]);
+        }
+    }
+}
+
+// which of the 16 keys are down on the keyboard or the first joypad
+fn keypad(input_state: Option<InputStateFn>) -> [bool; 16] {
+    let mut down = [false; 16];
+    let Some(input_state) = input_state else {
+        return down;
+    };
+    for c in KEYBOARD.chars() {
+        // the keyboard key codes of letters and digits are their lowercase ASCII codes
+        if let Some(key) = get_key(&c.to_string()) {
+            down[key as usize] |= input_state(0, RETRO_DEVICE_KEYBOARD, 0, c as c_uint) != 0;
+        }
+    }
+    for (button, key) in JOYPAD {
+        down[key as usize] |= input_state(0, RETRO_DEVICE_JOYPAD, 0, button) != 0;
+    }
+    down
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    if core().is_none() {
+        return 0;
+    }
+    // the largest a state gets, with a full stack, so the size stays the same while a game runs
+    let mut full = Interpreter::new();
+    full.stack = vec![0; STACK_SIZE];
+    full.save_state().len()
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = core();
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+    let state = core.interpreter.save_state();
+    if data.is_null() || state.len() > size {
+        return false;
+    }
+    let out = std::slice::from_raw_parts_mut(data as *mut u8, size);
+    out[..state.len()].copy_from_slice(&state);
+    out[state.len()..].fill(0);
+    true
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = core();
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+    if data.is_null() {
+        return false;
+    }
+    let state = std::slice::from_raw_parts(data as *const u8, size);
+    core.interpreter.load_state(state).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// The interpreter's memory as system RAM, valid until the game is unloaded.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match core().as_mut() {
+        Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => {
+            core.interpreter.memory.as_mut_ptr() as *mut c_void
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match core().as_ref() {
+        Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => core.interpreter.memory.len(),
+        _ => 0,
+    }
+}
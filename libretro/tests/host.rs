@@ -0,0 +1,328 @@
+//! A minimal libretro frontend driving the core through its C API, as RetroArch would.
+
+use std::ffi::{c_uint, c_void, CStr};
+use std::sync::{Mutex, MutexGuard};
+
+use chip8_libretro::ffi::*;
+use chip8_libretro::*;
+
+const WHITE: u32 = 0x00ff_ffff;
+const BLACK: u32 = 0;
+
+// what the frontend was handed, and the input it reports
+struct Host {
+    accept_format: bool,
+    pixel_format: Option<c_uint>,
+    // every environment command the core sent
+    commands: Vec<c_uint>,
+    frames: Vec<Vec<u32>>,
+    audio: Vec<i16>,
+    // (device, id) of the buttons held
+    held: Vec<(c_uint, c_uint)>,
+}
+
+static HOST: Mutex<Host> = Mutex::new(Host {
+    accept_format: true,
+    pixel_format: None,
+    commands: Vec::new(),
+    frames: Vec::new(),
+    audio: Vec::new(),
+    held: Vec::new(),
+});
+// the core is global, so one test runs it at a time
+static RUNNING: Mutex<()> = Mutex::new(());
+
+fn host() -> MutexGuard<'static, Host> {
+    HOST.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    let mut host = host();
+    host.commands.push(cmd);
+    // there's no log, so errors go unreported
+    if cmd != RETRO_ENVIRONMENT_SET_PIXEL_FORMAT || !host.accept_format {
+        return false;
+    }
+    host.pixel_format = Some(unsafe { *(data as *const c_uint) });
+    true
+}
+
+extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, pitch: usize) {
+    let mut frame = Vec::new();
+    for y in 0..height as usize {
+        let row = unsafe { (data as *const u8).add(y * pitch) as *const u32 };
+        frame.extend_from_slice(unsafe { std::slice::from_raw_parts(row, width as usize) });
+    }
+    host().frames.push(frame);
+}
+
+extern "C" fn audio_sample(left: i16, right: i16) {
+    host().audio.extend_from_slice(&[left, right]);
+}
+
+extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+    let samples = unsafe { std::slice::from_raw_parts(data, frames * 2) };
+    host().audio.extend_from_slice(samples);
+    frames
+}
+
+extern "C" fn input_poll() {}
+
+extern "C" fn input_state(port: c_uint, device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+    (port == 0 && host().held.contains(&(device, id))) as i16
+}
+
+fn program(opcodes: &[u16]) -> Vec<u8> {
+    opcodes.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+// load `rom` into a freshly set up core
+fn start(rom: &[u8]) -> MutexGuard<'static, ()> {
+    let running = RUNNING.lock().unwrap_or_else(|err| err.into_inner());
+    {
+        let mut host = host();
+        host.accept_format = true;
+        host.pixel_format = None;
+        host.commands.clear();
+        host.frames.clear();
+        host.audio.clear();
+        host.held.clear();
+    }
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample(audio_sample);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    assert!(unsafe { load(rom) });
+    running
+}
+
+unsafe fn load(rom: &[u8]) -> bool {
+    let game = GameInfo {
+        path: std::ptr::null(),
+        data: rom.as_ptr() as *const c_void,
+        size: rom.len(),
+        meta: std::ptr::null(),
+    };
+    retro_load_game(&game)
+}
+
+fn run(frames: usize) -> Vec<u32> {
+    for _ in 0..frames {
+        retro_run();
+    }
+    host().frames.last().unwrap().clone()
+}
+
+fn stop(running: MutexGuard<'static, ()>) {
+    retro_unload_game();
+    retro_deinit();
+    drop(running);
+}
+
+#[test]
+fn system_info() {
+    assert_eq!(retro_api_version(), 1);
+    let mut info = std::mem::MaybeUninit::uninit();
+    let info = unsafe {
+        retro_get_system_info(info.as_mut_ptr());
+        info.assume_init()
+    };
+    let text = |ptr| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap();
+    assert_eq!(text(info.library_name), "CHIP-8");
+    assert_eq!(text(info.library_version), "0.1.0");
+    assert_eq!(text(info.valid_extensions), "ch8|c8");
+    assert!(!info.need_fullpath);
+
+    let mut av = std::mem::MaybeUninit::uninit();
+    let av = unsafe {
+        retro_get_system_av_info(av.as_mut_ptr());
+        av.assume_init()
+    };
+    assert_eq!((av.geometry.base_width, av.geometry.base_height), (64, 32));
+    assert_eq!(av.geometry.aspect_ratio, 2.0);
+    assert_eq!(av.timing.fps, 60.0);
+    assert_eq!(av.timing.sample_rate, 44_100.0);
+}
+
+#[test]
+fn loading() {
+    let running = start(&program(&[0x1200]));
+    assert_eq!(host().pixel_format, Some(RETRO_PIXEL_FORMAT_XRGB8888));
+    assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), 4096);
+    assert!(!unsafe { load(&[0; 4096]) }, "too large for memory");
+    host().accept_format = false;
+    assert!(!unsafe { load(&program(&[0x1200])) }, "no XRGB8888");
+    assert!(!unsafe { retro_load_game(std::ptr::null()) });
+    stop(running);
+}
+
+#[test]
+fn video_and_keyboard() {
+    // wait for a key and draw its digit in the top left
+    let running = start(&program(&[0xF00A, 0xF029, 0xD125, 0x1206]));
+    let frame = run(3);
+    assert_eq!(frame.len(), 64 * 32);
+    assert!(frame.iter().all(|&pixel| pixel == WHITE));
+
+    // W is 5 on the keypad, it counts once let go
+    host().held.push((RETRO_DEVICE_KEYBOARD, 'w' as c_uint));
+    run(2);
+    host().held.clear();
+    let frame = run(2);
+    // the top of 5 is 0xF0
+    assert_eq!(frame[..5], [BLACK, BLACK, BLACK, BLACK, WHITE]);
+    // its second row is 0x80
+    assert_eq!(frame[64..66], [BLACK, WHITE]);
+    stop(running);
+}
+
+#[test]
+fn joypad() {
+    let running = start(&program(&[0xF00A, 0xF029, 0xD125, 0x1206]));
+    // up is 2 on the keypad
+    host()
+        .held
+        .push((RETRO_DEVICE_JOYPAD, RETRO_DEVICE_ID_JOYPAD_UP));
+    run(2);
+    host().held.clear();
+    let frame = run(2);
+    // the second row of 2 is 0x10
+    assert_eq!(frame[64..68], [WHITE, WHITE, WHITE, BLACK]);
+    stop(running);
+}
+
+#[test]
+fn audio_follows_the_sound_timer() {
+    // sound for 16 frames
+    let running = start(&program(&[0x6010, 0xF018, 0x1204]));
+    run(1);
+    let first = std::mem::take(&mut host().audio);
+    assert_eq!(first.len(), SAMPLES_PER_FRAME * 2);
+    assert!(first.iter().any(|&sample| sample > 0));
+    assert!(first.iter().any(|&sample| sample < 0));
+    // both channels the same
+    assert!(first.chunks_exact(2).all(|pair| pair[0] == pair[1]));
+
+    run(20);
+    let audio = std::mem::take(&mut host().audio);
+    assert_eq!(audio.len(), SAMPLES_PER_FRAME * 2 * 20);
+    let last = &audio[audio.len() - SAMPLES_PER_FRAME * 2..];
+    assert!(last.iter().all(|&sample| sample == 0));
+    stop(running);
+}
+
+#[test]
+fn shortest_sound_is_a_frame() {
+    // a timer of 1 sounds for the frame it was set in
+    let running = start(&program(&[0x6001, 0xF018, 0x1204]));
+    run(2);
+    let audio = std::mem::take(&mut host().audio);
+    let (first, second) = audio.split_at(SAMPLES_PER_FRAME * 2);
+    assert!(first.iter().any(|&sample| sample > 0));
+    assert!(second.iter().all(|&sample| sample == 0));
+    stop(running);
+}
+
+// counts up, drawing the last digit of the count every frame
+const COUNTER: [u16; 10] = [
+    0x6001, // v0 := 1
+    0xF015, // delay := v0
+    0xF107, // v1 := delay
+    0x3100, // if v1 != 0 then
+    0x1204, // jump 0x204, until the frame ends
+    0x7201, // v2 += 1
+    0xF229, // i := hex v2
+    0x00E0, // clear
+    0xD345, // sprite v3 v4 5
+    0x1200, // jump 0x200
+];
+
+#[test]
+fn save_states() {
+    let running = start(&program(&COUNTER));
+    run(5);
+    let size = retro_serialize_size();
+    assert!(size > 4096 + 64 * 32);
+    let mut state = vec![0u8; size];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, size) });
+    assert!(!unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, 100) });
+
+    let memory = retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8;
+    let after: Vec<_> = (0..3).map(|_| run(1)).collect();
+    assert_ne!(after[0], after[1]);
+
+    assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, size) });
+    let again: Vec<_> = (0..3).map(|_| run(1)).collect();
+    assert_eq!(again, after);
+    // memory didn't move
+    assert_eq!(
+        retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8,
+        memory
+    );
+    assert_eq!(unsafe { *memory.add(0x200) }, 0x60);
+
+    // failed loads leave the game as it was
+    let serialize = || {
+        let mut state = vec![0u8; size];
+        assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, size) });
+        state
+    };
+    let before = serialize();
+    let garbage = vec![0xAAu8; size];
+    assert!(!unsafe { retro_unserialize(garbage.as_ptr() as *const c_void, size) });
+    assert!(!unsafe { retro_unserialize(state.as_ptr() as *const c_void, 50) });
+    assert_eq!(serialize(), before);
+    stop(running);
+}
+
+#[test]
+fn reset() {
+    let running = start(&program(&COUNTER));
+    let first = run(1);
+    let memory = retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM);
+    assert_ne!(run(4), first);
+    retro_reset();
+    assert_eq!(run(1), first);
+    assert_eq!(retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM), memory);
+    stop(running);
+}
+
+#[test]
+fn failed_games_stop() {
+    // returns with nothing on the stack in the first frame
+    let running = start(&program(&[0x7001, 0x00EE]));
+    assert!(host()
+        .commands
+        .contains(&RETRO_ENVIRONMENT_GET_LOG_INTERFACE));
+    let frame = run(1);
+    // frames still come, of a game that's no longer running
+    assert_eq!(run(3), frame);
+    assert_eq!(host().frames.len(), 4);
+    let mut state = vec![0u8; retro_serialize_size()];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+    stop(running);
+}
+
+#[test]
+fn serialize_size() {
+    // calls and returns over and over, the size doesn't change with the stack
+    let running = start(&program(&[0x2204, 0x1200, 0x00EE]));
+    let size = retro_serialize_size();
+    for _ in 0..5 {
+        run(1);
+        assert_eq!(retro_serialize_size(), size);
+    }
+    stop(running);
+
+    // a full stack fits, and the call that would overflow it stops the game
+    let running = start(&program(&[0x2200]));
+    run(1);
+    assert_eq!(retro_serialize_size(), size);
+    let mut state = vec![0u8; size];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, size) });
+    assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, size) });
+    stop(running);
+}
@@ -0,0 +1,51 @@
+//! The arithmetic of `7XNN`, `8XYN` and `FX1E`. Everything wraps around like the 8-bit
+//! registers of the original machine instead of panicking on overflow.
+
+use crate::Quirks;
+
+/// What an `8XYN` instruction stores in VX, and in VF after it for the instructions that
+/// set a flag. VF is written last, so when X is F the flag is what VF ends up holding.
+/// Both operands are read before anything is written, so Y can be F too.
+/// `None` for the values of N that aren't instructions.
+pub fn register_op(n: u8, vx: u8, vy: u8, quirks: &Quirks) -> Option<(u8, Option<u8>)> {
+    // the VIP ran the logic operations through code that clobbered VF
+    let reset = quirks.vf_reset.then_some(0);
+    // VY shifted into VX, or VX shifted in place
+    let shifted = if quirks.shift_vx { vx } else { vy };
+
+    Some(match n {
+        0x0 => (vy, None),
+        0x1 => (vx | vy, reset),
+        0x2 => (vx & vy, reset),
+        0x3 => (vx ^ vy, reset),
+        // VF is 1 on a carry
+        0x4 => {
+            let (sum, carry) = vx.overflowing_add(vy);
+            (sum, Some(carry as u8))
+        }
+        // VF is 0 on a borrow
+        0x5 => subtract(vx, vy),
+        0x7 => subtract(vy, vx),
+        // VF is the bit shifted out
+        0x6 => (shifted >> 1, Some(shifted & 0x01)),
+        0xE => (shifted << 1, Some(shifted >> 7)),
+        _ => return None,
+    })
+}
+
+fn subtract(a: u8, b: u8) -> (u8, Option<u8>) {
+    let (difference, borrow) = a.overflowing_sub(b);
+    (difference, Some(!borrow as u8))
+}
+
+/// `7XNN`, which has no carry flag.
+pub fn add_value(vx: u8, nn: u8) -> u8 {
+    vx.wrapping_add(nn)
+}
+
+/// The new I after `FX1E` and, with the Amiga quirk, VF: 1 when I went past 0xFFF.
+pub fn add_index(index: u16, vx: u8, quirks: &Quirks) -> (u16, Option<u8>) {
+    let sum = index.wrapping_add(vx as u16);
+    let overflow = index <= 0xFFF && sum > 0xFFF;
+    (sum, quirks.index_overflow.then_some(overflow as u8))
+}
@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::symbols::SymbolMap;
+use crate::OFFSET;
+
+// how deep macros can expand inside other macros, which stops one that uses itself
+const MACRO_DEPTH: usize = 64;
+
+/// An assembly error and the source line that caused it.
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assemble Octo source into a ROM image that loads at `OFFSET`,
+/// along with the labels and source lines of `file_name` for the debugger.
+pub fn assemble(source: &str, file_name: &str) -> Result<(Vec<u8>, SymbolMap), AsmError> {
+    let mut asm = Assembler::new(source);
+    let mut symbols = SymbolMap::new(file_name);
+    while asm.pos < asm.tokens.len() {
+        let addr = asm.here();
+        let line = asm.tokens[asm.pos].line;
+        asm.statement()?;
+        if asm.here() != addr {
+            symbols.add_line(addr, line);
+        }
+    }
+    for (name, &addr) in &asm.labels {
+        symbols.add_label(addr, name);
+    }
+    Ok((asm.finish()?, symbols))
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    // macro expansions this token came from
+    depth: usize,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+// a 12 bit address operand waiting for its label to be defined
+struct Fixup {
+    offset: usize,
+    label: String,
+    line: usize,
+}
+
+enum Block {
+    // `if ... begin`, offset of the jump to patch with the `else`/`end` address
+    Begin(usize),
+    Else(usize),
+    // `loop`, address of its start and the `while` jumps to patch with the exit address
+    Loop(u16, Vec<usize>),
+}
+
+struct Assembler {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+    out: Vec<u8>,
+    labels: HashMap<String, u16>,
+    consts: HashMap<String, i32>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+}
+
+impl Assembler {
+    fn new(source: &str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                let code = line.split('#').next().unwrap_or("");
+                code.split_whitespace().map(move |text| Token {
+                    text: text.to_string(),
+                    line: i + 1,
+                    depth: 0,
+                })
+            })
+            .collect();
+
+        Self {
+            tokens,
+            pos: 0,
+            line: 1,
+            out: vec![],
+            labels: HashMap::new(),
+            consts: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: vec![],
+            blocks: vec![],
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, AsmError> {
+        Err(AsmError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Result<String, AsmError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                self.line = token.line;
+                Ok(token.text.clone())
+            }
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token != expected {
+            return self.error(format!("expected '{expected}', found '{token}'"));
+        }
+        Ok(())
+    }
+
+    fn here(&self) -> u16 {
+        (OFFSET + self.out.len()) as u16
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.out.extend(opcode.to_be_bytes());
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token.as_str() {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name.clone(), self.here()).is_some() {
+                    return self.error(format!("label '{name}' is already defined"));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.consts.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":macro" => self.define_macro()?,
+            ":call" => {
+                let addr = self.address()?;
+                self.emit(0x2000 | addr);
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.out.push(byte);
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "jump" => {
+                let addr = self.address()?;
+                self.emit(0x1000 | addr);
+            }
+            "jump0" => {
+                let addr = self.address()?;
+                self.emit(0xB000 | addr);
+            }
+            "i" => self.index()?,
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.register()? as u16;
+                self.emit(if token == "delay" { 0xF015 } else { 0xF018 } | x << 8);
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.value()?;
+                if !(0..=0xF).contains(&n) {
+                    return self.error(format!("sprite height {n} is out of range"));
+                }
+                self.emit(0xD000 | x << 8 | y << 4 | n as u16);
+            }
+            "bcd" | "save" | "load" => {
+                let x = self.register()? as u16;
+                let opcode = match token.as_str() {
+                    "bcd" => 0xF033,
+                    "save" => 0xF055,
+                    _ => 0xF065,
+                };
+                self.emit(opcode | x << 8);
+            }
+            "if" => {
+                let skip = self.condition()?;
+                match self.next()?.as_str() {
+                    "then" => self.emit(skip),
+                    "begin" => {
+                        self.emit(invert(skip));
+                        self.blocks.push(Block::Begin(self.out.len()));
+                        self.emit(0x1000);
+                    }
+                    other => {
+                        return self.error(format!("expected 'then' or 'begin', found '{other}'"))
+                    }
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::Begin(jump)) => {
+                    self.blocks.push(Block::Else(self.out.len()));
+                    self.emit(0x1000);
+                    self.patch(jump, self.here());
+                }
+                _ => return self.error("'else' without 'begin'"),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::Begin(jump) | Block::Else(jump)) => self.patch(jump, self.here()),
+                _ => return self.error("'end' without 'begin'"),
+            },
+            "loop" => self.blocks.push(Block::Loop(self.here(), vec![])),
+            "while" => {
+                let skip = self.condition()?;
+                self.emit(invert(skip));
+                let jump = self.out.len();
+                self.emit(0x1000);
+                match self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|b| matches!(b, Block::Loop(..)))
+                {
+                    Some(Block::Loop(_, exits)) => exits.push(jump),
+                    _ => return self.error("'while' outside of a loop"),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop(start, exits)) => {
+                    self.emit(0x1000 | start);
+                    for jump in exits {
+                        self.patch(jump, self.here());
+                    }
+                }
+                _ => return self.error("'again' without 'loop'"),
+            },
+            _ if self.macros.contains_key(&token) => self.expand_macro(&token)?,
+            _ if self.is_register(&token) => {
+                self.pos -= 1;
+                self.register_statement()?;
+            }
+            _ => {
+                if let Ok(value) = self.literal(&token) {
+                    let byte = self.to_byte(value)?;
+                    self.out.push(byte);
+                } else {
+                    // a bare label name calls it
+                    self.pos -= 1;
+                    let addr = self.address()?;
+                    self.emit(0x2000 | addr);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        if !self.blocks.is_empty() {
+            return self.error("unterminated 'begin' or 'loop' block");
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            match self.labels.get(&fixup.label) {
+                Some(&addr) => self.patch(fixup.offset, addr),
+                None => {
+                    return Err(AsmError {
+                        line: fixup.line,
+                        message: format!("undefined label '{}'", fixup.label),
+                    })
+                }
+            }
+        }
+        if OFFSET + self.out.len() > 0x1000 {
+            return self.error(format!(
+                "program is {} bytes, too large to fit in memory",
+                self.out.len()
+            ));
+        }
+        Ok(self.out)
+    }
+
+    fn patch(&mut self, offset: usize, addr: u16) {
+        self.out[offset] = (self.out[offset] & 0xF0) | (addr >> 8) as u8;
+        self.out[offset + 1] = addr as u8;
+    }
+
+    fn name(&mut self) -> Result<String, AsmError> {
+        let name = self.next()?;
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            && !name.starts_with(|c: char| c.is_ascii_digit());
+        if !valid {
+            return self.error(format!("'{name}' is not a valid name"));
+        }
+        Ok(name)
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.name()?;
+        let mut params = vec![];
+        loop {
+            let token = self.next()?;
+            if token == "{" {
+                break;
+            }
+            params.push(token);
+        }
+
+        let mut body = vec![];
+        let mut depth = 1;
+        loop {
+            let token = self.tokens.get(self.pos).cloned();
+            let Some(token) = token else {
+                return self.error(format!("macro '{name}' is missing its closing '}}'"));
+            };
+            self.pos += 1;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                break;
+            }
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), AsmError> {
+        // the macro's name was the last token read
+        let depth = self.tokens[self.pos - 1].depth + 1;
+        if depth > MACRO_DEPTH {
+            return self.error(format!(
+                "macro '{name}' is nested more than {MACRO_DEPTH} deep"
+            ));
+        }
+        let count = self.macros[name].params.len();
+        let mut args = HashMap::new();
+        for i in 0..count {
+            let arg = self.next()?;
+            args.insert(self.macros[name].params[i].clone(), arg);
+        }
+
+        let expansion: Vec<Token> = self.macros[name]
+            .body
+            .iter()
+            // errors and the symbol map point at the invocation
+            .map(|token| Token {
+                text: args.get(&token.text).unwrap_or(&token.text).clone(),
+                line: self.line,
+                depth,
+            })
+            .collect();
+        self.tokens.splice(self.pos..self.pos, expansion);
+        Ok(())
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.aliases.contains_key(token) || parse_register(token).is_some()
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        match self
+            .aliases
+            .get(&token)
+            .copied()
+            .or_else(|| parse_register(&token))
+        {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register, found '{token}'")),
+        }
+    }
+
+    fn literal(&self, token: &str) -> Result<i32, AsmError> {
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, token),
+        };
+        let parsed = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16)
+        } else if let Some(bin) = digits.strip_prefix("0b") {
+            i32::from_str_radix(bin, 2)
+        } else {
+            digits.parse()
+        };
+        match parsed {
+            Ok(value) if negative => Ok(-value),
+            Ok(value) => Ok(value),
+            Err(_) => self.error(format!("'{token}' is not a number")),
+        }
+    }
+
+    fn value(&mut self) -> Result<i32, AsmError> {
+        let token = self.next()?;
+        if let Some(&value) = self.consts.get(&token) {
+            return Ok(value);
+        }
+        if let Some(&addr) = self.labels.get(&token) {
+            return Ok(addr as i32);
+        }
+        self.literal(&token)
+    }
+
+    fn to_byte(&self, value: i32) -> Result<u8, AsmError> {
+        if !(-128..=255).contains(&value) {
+            return self.error(format!("{value} does not fit in a byte"));
+        }
+        Ok(value as u8)
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let value = self.value()?;
+        self.to_byte(value)
+    }
+
+    // a 12 bit address, possibly a label that is defined later
+    fn address(&mut self) -> Result<u16, AsmError> {
+        let token = self.next()?;
+        if let Some(&addr) = self.labels.get(&token) {
+            return Ok(addr);
+        }
+        let value = match self.consts.get(&token) {
+            Some(&value) => value,
+            None => match self.literal(&token) {
+                Ok(value) => value,
+                Err(_) => {
+                    self.check_label(&token)?;
+                    self.fixups.push(Fixup {
+                        offset: self.out.len(),
+                        label: token,
+                        line: self.line,
+                    });
+                    return Ok(0);
+                }
+            },
+        };
+        if !(0..=0xFFF).contains(&value) {
+            return self.error(format!("address {value:#x} is out of range"));
+        }
+        Ok(value as u16)
+    }
+
+    fn check_label(&self, token: &str) -> Result<(), AsmError> {
+        if token.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            Ok(())
+        } else {
+            self.error(format!("unexpected '{token}'"))
+        }
+    }
+
+    fn index(&mut self) -> Result<(), AsmError> {
+        match self.next()?.as_str() {
+            ":=" => {
+                if self.peek() == Some("hex") {
+                    self.pos += 1;
+                    let x = self.register()? as u16;
+                    self.emit(0xF029 | x << 8);
+                } else {
+                    let addr = self.address()?;
+                    self.emit(0xA000 | addr);
+                }
+            }
+            "+=" => {
+                let x = self.register()? as u16;
+                self.emit(0xF01E | x << 8);
+            }
+            other => return self.error(format!("unknown operator 'i {other}'")),
+        }
+        Ok(())
+    }
+
+    // returns the skip instruction for `if <condition> then`
+    fn condition(&mut self) -> Result<u16, AsmError> {
+        let x = self.register()? as u16;
+        let op = self.next()?;
+        match op.as_str() {
+            "key" => return Ok(0xE0A1 | x << 8),
+            "-key" => return Ok(0xE09E | x << 8),
+            "==" | "!=" => (),
+            _ => return self.error(format!("unsupported comparison '{op}'")),
+        }
+
+        let opcode = if self.peek().is_some_and(|t| self.is_register(t)) {
+            let y = self.register()? as u16;
+            (if op == "==" { 0x9000 } else { 0x5000 }) | x << 8 | y << 4
+        } else {
+            let nn = self.byte()? as u16;
+            (if op == "==" { 0x4000 } else { 0x3000 }) | x << 8 | nn
+        };
+        Ok(opcode)
+    }
+
+    fn register_statement(&mut self) -> Result<(), AsmError> {
+        let x = self.register()? as u16;
+        let op = self.next()?;
+
+        if self.peek().is_some_and(|t| self.is_register(t)) {
+            let y = self.register()? as u16;
+            let n = match op.as_str() {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                _ => return self.error(format!("unknown operator '{op}'")),
+            };
+            self.emit(0x8000 | x << 8 | y << 4 | n);
+            return Ok(());
+        }
+
+        let opcode = match (op.as_str(), self.peek()) {
+            (":=", Some("random")) => {
+                self.pos += 1;
+                0xC000 | self.byte()? as u16
+            }
+            (":=", Some("key")) => {
+                self.pos += 1;
+                0xF00A
+            }
+            (":=", Some("delay")) => {
+                self.pos += 1;
+                0xF007
+            }
+            (":=", _) => 0x6000 | self.byte()? as u16,
+            ("+=", _) => 0x7000 | self.byte()? as u16,
+            ("-=", _) => 0x7000 | self.byte()?.wrapping_neg() as u16,
+            _ => return self.error(format!("unknown operator '{op}'")),
+        };
+        self.emit(opcode | x << 8);
+        Ok(())
+    }
+}
+
+fn parse_register(token: &str) -> Option<u8> {
+    let digit = token.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+// the skip instruction with the opposite condition
+fn invert(skip: u16) -> u16 {
+    match skip >> 12 {
+        0x3 | 0x4 => skip ^ 0x7000,
+        0x5 | 0x9 => skip ^ 0xC000,
+        // EX9E <-> EXA1
+        _ => skip ^ 0x003F,
+    }
+}
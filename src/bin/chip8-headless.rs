@@ -0,0 +1,256 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use chip8::headless::{self, KeyPress, StopCondition};
+use chip8::record;
+use chip8::{screen, ExeError, Filter, Interpreter, Palette, Persistence, Quirks, Timing};
+
+const USAGE: &str = "usage: chip8-headless <rom.ch8> [options]
+  --quirks <preset>           chip8 (default), superchip or xochip
+  --display-wait              end the frame after each sprite drawn, as the VIP did
+  --timing <mode>             ipf:<n> instructions per frame (default ipf:500),
+                              ips:<n> instructions per second, or vip for COSMAC VIP cycle timing
+  --frames <n>                run for at most n frames (default 600)
+  --until-pc <addr>           stop when the PC reaches addr (hex)
+  --until-halt                stop when the program jumps to itself
+  --key <frame>:<key>[:<n>]   hold hex key from frame for n frames (default 1)
+  --keys <file>               read --key entries from a file, one per line
+  --screen <file>             write the screen, as PNG for .png files and text otherwise (default stdout)
+  --scale <n>                 size of a CHIP-8 pixel in PNGs (default 1)
+  --palette <palette>         colors of PNGs and GIFs: mono (default), green, amber, octo, lcd
+                              or rrggbb,rrggbb[,rrggbb,rrggbb] starting with the background
+  --persistence <mode>        fading of pixels in PNGs and GIFs: off (default),
+                              decay:<strength> or blend:<strength>, strength from 0 to 1
+  --filter <filters>          effects on scaled up PNGs and GIFs, comma separated: scanlines,
+                              grid, rounded and scale2x (twice for scale4x), or none (default)
+  --screenshot <dir>          also save a PNG named after the ROM and the time in dir
+  --record-gif <file>         record the screen as an animated GIF
+  --record-frames <dir>       record every frame as a numbered PNG
+  --record-wav <file>         record the buzzer as a WAV, 735 samples a frame
+  --state <file>              write the registers as JSON, - for stdout
+
+exits with 1 when an instruction fails and 3 when a stop condition was never reached";
+
+fn main() {
+    let mut rom = None;
+    let mut frames = 600;
+    let mut stop = StopCondition::default();
+    let mut presses = Vec::new();
+    let mut screen_file = None;
+    let mut scale = 1;
+    let mut state_file = None;
+    let mut screenshot_dir = None;
+    let mut outputs = record::Outputs::default();
+    let mut quirks = Quirks::default();
+    let mut display_wait = false;
+    let mut timing = Timing::default();
+    let mut palette = Palette::default();
+    let mut persistence = Persistence::default();
+    let mut filters = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                quirks = args
+                    .next()
+                    .and_then(|name| Quirks::preset(&name))
+                    .unwrap_or_else(usage)
+            }
+            "--display-wait" => display_wait = true,
+            "--palette" => {
+                palette = args
+                    .next()
+                    .and_then(|spec| Palette::parse(&spec))
+                    .unwrap_or_else(usage)
+            }
+            "--persistence" => {
+                persistence = args
+                    .next()
+                    .and_then(|mode| Persistence::parse(&mode))
+                    .unwrap_or_else(usage)
+            }
+            "--filter" => {
+                filters = args
+                    .next()
+                    .and_then(|list| Filter::parse_list(&list))
+                    .unwrap_or_else(usage)
+            }
+            "--timing" => {
+                timing = args
+                    .next()
+                    .and_then(|mode| Timing::parse(&mode))
+                    .unwrap_or_else(usage)
+            }
+            "--frames" => {
+                frames = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or_else(usage)
+            }
+            "--until-pc" => {
+                stop.pc = args
+                    .next()
+                    .and_then(|addr| usize::from_str_radix(addr.trim_start_matches("0x"), 16).ok());
+                if stop.pc.is_none() {
+                    usage()
+                }
+            }
+            "--until-halt" => stop.halt = true,
+            "--key" => presses.push(
+                args.next()
+                    .and_then(|press| KeyPress::parse(&press))
+                    .unwrap_or_else(usage),
+            ),
+            "--keys" => {
+                let file = args.next().unwrap_or_else(usage);
+                let text = std::fs::read_to_string(&file).unwrap_or_else(|err| {
+                    eprintln!("{file}: {err}");
+                    std::process::exit(2);
+                });
+                for (i, line) in text.lines().enumerate() {
+                    let line = line.split('#').next().unwrap().trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let Some(press) = KeyPress::parse(line) else {
+                        eprintln!("{file}: line {}: expected <frame>:<key>[:<n>]", i + 1);
+                        std::process::exit(2);
+                    };
+                    presses.push(press);
+                }
+            }
+            "--screen" => screen_file = Some(args.next().unwrap_or_else(usage)),
+            "--scale" => {
+                scale = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or_else(usage)
+            }
+            "--state" => state_file = Some(args.next().unwrap_or_else(usage)),
+            "--screenshot" => screenshot_dir = Some(args.next().unwrap_or_else(usage)),
+            "--record-gif" => outputs.gif = Some(args.next().unwrap_or_else(usage).into()),
+            "--record-frames" => outputs.frames = Some(args.next().unwrap_or_else(usage).into()),
+            "--record-wav" => outputs.wav = Some(args.next().unwrap_or_else(usage).into()),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if rom.is_none() && !arg.starts_with("--") => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+    let rom: String = rom.unwrap_or_else(usage);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.quirks = quirks;
+    interpreter.quirks.display_wait |= display_wait;
+    interpreter.timing = timing;
+    interpreter.palette = palette;
+    interpreter.persistence = persistence;
+    interpreter.filters = filters;
+    if let Err(err) = interpreter.load(&rom) {
+        eprintln!("{rom}: {err}");
+        std::process::exit(2);
+    }
+
+    outputs.scale = scale;
+    let recording = outputs.gif.is_some() || outputs.frames.is_some() || outputs.wav.is_some();
+    let mut recorder = match recording.then(|| record::Recorder::start(&outputs)) {
+        Some(Ok(recorder)) => Some(recorder),
+        Some(Err(err)) => {
+            eprintln!("could not start recording: {err}");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let (frame, result) = match &mut recorder {
+        Some(recorder) => headless::run_with(
+            &mut interpreter,
+            frames,
+            &presses,
+            &mut stop,
+            &mut [recorder],
+        ),
+        None => headless::run(&mut interpreter, frames, &presses, &mut stop),
+    };
+    let error = result.err();
+    let record_result = recorder.map_or(Ok(()), record::Recorder::finish);
+
+    let screen_result = match screen_file.as_deref() {
+        Some(path) if path.ends_with(".png") => {
+            screen::write_png(&interpreter, Path::new(path), scale)
+        }
+        Some(path) => std::fs::write(path, screen::to_text(&interpreter.screen)),
+        None => {
+            print!("{}", screen::to_text(&interpreter.screen));
+            Ok(())
+        }
+    };
+    let screenshot_result = match screenshot_dir.as_deref() {
+        Some(dir) => headless::screenshot(&interpreter, &rom, Path::new(dir), scale)
+            .map(|path| eprintln!("saved {}", path.display())),
+        None => Ok(()),
+    };
+    let state = state_json(&interpreter, frame, error);
+    let state_result = match state_file.as_deref() {
+        Some("-") => {
+            println!("{state}");
+            Ok(())
+        }
+        Some(path) => std::fs::write(path, state + "\n"),
+        None => Ok(()),
+    };
+    if let Err(err) = screen_result
+        .and(screenshot_result)
+        .and(record_result)
+        .and(state_result)
+    {
+        eprintln!("could not write output: {err}");
+        std::process::exit(2);
+    }
+
+    if let Some(err) = error {
+        eprintln!("frame {frame}: {err}");
+        std::process::exit(1);
+    }
+    if stop.is_set() && !stop.reached {
+        eprintln!("stop condition not reached in {frames} frames");
+        std::process::exit(3);
+    }
+}
+
+fn state_json(interpreter: &Interpreter, frames: u64, error: Option<ExeError>) -> String {
+    let list = |values: &mut dyn Iterator<Item = u16>| {
+        values.map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+    };
+    let mut json = String::from("{\n");
+    writeln!(json, "  \"frames\": {frames},").unwrap();
+    writeln!(
+        json,
+        "  \"registers\": [{}],",
+        list(&mut interpreter.registers.iter().map(|&v| v as u16))
+    )
+    .unwrap();
+    writeln!(json, "  \"i\": {},", interpreter.index).unwrap();
+    writeln!(json, "  \"pc\": {},", interpreter.program_counter).unwrap();
+    writeln!(
+        json,
+        "  \"stack\": [{}],",
+        list(&mut interpreter.stack.iter().copied())
+    )
+    .unwrap();
+    writeln!(json, "  \"delay_timer\": {},", interpreter.delay_timer).unwrap();
+    writeln!(json, "  \"sound_timer\": {},", interpreter.sound_timer).unwrap();
+    match error {
+        Some(err) => writeln!(json, "  \"error\": \"{err}\"").unwrap(),
+        None => writeln!(json, "  \"error\": null").unwrap(),
+    }
+    json.push('}');
+    json
+}
+
+fn usage<T>() -> T {
+    eprintln!("{USAGE}");
+    std::process::exit(2);
+}
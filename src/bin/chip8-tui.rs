@@ -0,0 +1,327 @@
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use chip8::config::Config;
+use chip8::debugger::Debugger;
+use chip8::scheduler::{Scheduler, Speed};
+use chip8::tui::{self, Controls, HeldKeys};
+use chip8::{Interpreter, Palette, Persistence, Quirks, Timing};
+use chip8::{TARGET_FPS, WIDTH};
+
+const USAGE: &str = "usage: chip8-tui <rom.ch8> [options]
+  --quirks <preset>      chip8 (default), superchip or xochip
+  --display-wait         end the frame after each sprite drawn, as the VIP did
+  --timing <mode>        ipf:<n> (default ipf:500), ips:<n> or vip
+  --palette <palette>    mono (default), green, amber, octo, lcd or rrggbb,rrggbb[,..]
+  --persistence <mode>   off (default), decay:<strength> or blend:<strength>
+  --config <file>        palette and persistence per ROM (default chip8.conf if it exists)
+  --mono                 draw with half blocks in the terminal's own colors
+  --panel                start with the register panel shown
+  --break <addr>         pause before the instruction at addr (hex), can be repeated
+
+keys: 1234 qwer asdf zxcv for the keypad, F1 panel, F2 pause, F3 next frame,
+      F10 next instruction, F4 1x speed, F5 slower, F6 faster, F12 screenshot, Esc quit";
+
+const CONFIG: &str = "chip8.conf";
+// the screen sits in a border, the panel to its right
+const PANEL_X: u16 = WIDTH as u16 + 3;
+const STATUS_Y: u16 = tui::ROWS as u16 + 2;
+
+fn main() {
+    let mut rom = None;
+    let mut quirks = Quirks::default();
+    let mut display_wait = false;
+    let mut timing = Timing::default();
+    let mut palette = None;
+    let mut persistence = None;
+    let mut config_path = None;
+    let mut mono = false;
+    let mut show_panel = false;
+    let mut breakpoints = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                quirks = args
+                    .next()
+                    .and_then(|name| Quirks::preset(&name))
+                    .unwrap_or_else(usage)
+            }
+            "--display-wait" => display_wait = true,
+            "--timing" => {
+                timing = args
+                    .next()
+                    .and_then(|mode| Timing::parse(&mode))
+                    .unwrap_or_else(usage)
+            }
+            "--palette" => {
+                palette = Some(
+                    args.next()
+                        .and_then(|spec| Palette::parse(&spec))
+                        .unwrap_or_else(usage),
+                )
+            }
+            "--persistence" => {
+                persistence = Some(
+                    args.next()
+                        .and_then(|mode| Persistence::parse(&mode))
+                        .unwrap_or_else(usage),
+                )
+            }
+            "--config" => config_path = Some(args.next().unwrap_or_else(usage)),
+            "--mono" => mono = true,
+            "--panel" => show_panel = true,
+            "--break" => breakpoints.push(
+                args.next()
+                    .and_then(|addr| usize::from_str_radix(addr.trim_start_matches("0x"), 16).ok())
+                    .unwrap_or_else(usage),
+            ),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if rom.is_none() && !arg.starts_with("--") => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+    let rom: String = rom.unwrap_or_else(usage);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.quirks = quirks;
+    interpreter.quirks.display_wait |= display_wait;
+    interpreter.timing = timing;
+    if let Err(err) = interpreter.load(&rom) {
+        eprintln!("{rom}: {err}");
+        std::process::exit(2);
+    }
+    let config_path = config_path.or_else(|| Path::new(CONFIG).exists().then(|| CONFIG.into()));
+    let config = match config_path {
+        Some(path) => Config::load(&path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            std::process::exit(2);
+        }),
+        None => Config::default(),
+    };
+    interpreter.palette = palette
+        .or_else(|| config.palette_for(&rom))
+        .unwrap_or_default();
+    interpreter.persistence = persistence
+        .or_else(|| config.persistence_for(&rom))
+        .unwrap_or_default();
+
+    let mut debugger = Debugger::new();
+    for addr in breakpoints {
+        debugger.add_breakpoint(addr);
+    }
+
+    let mut tui = Tui {
+        interpreter,
+        controls: Controls::new(debugger, show_panel),
+        rom,
+        mono,
+    };
+    if let Err(err) = tui.run() {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn usage<T>() -> T {
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
+
+struct Tui {
+    interpreter: Interpreter,
+    controls: Controls,
+    rom: String,
+    mono: bool,
+}
+
+impl Tui {
+    // set the terminal up, run until Esc and put the terminal back even when that fails
+    fn run(&mut self) -> io::Result<()> {
+        let mut out = BufWriter::new(io::stdout());
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        // terminals with the kitty keyboard protocol tell when keys are let go
+        let reports_release = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_release {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        let result = self.event_loop(&mut out, HeldKeys::new(reports_release));
+
+        if reports_release {
+            execute!(out, PopKeyboardEnhancementFlags)?;
+        }
+        execute!(out, Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write, mut held: HeldKeys) -> io::Result<()> {
+        let mut scheduler = Scheduler::new(TARGET_FPS, Instant::now());
+        // at unlimited speed, how long frames run for between redraws
+        let unlimited_slice = Duration::from_nanos(1_000_000_000 / TARGET_FPS);
+        // what was drawn last, a frame that looks the same isn't sent again
+        let mut last = Vec::new();
+        loop {
+            let mut advance = false;
+            let mut redraw = false;
+            let timeout = scheduler
+                .next_frame()
+                .saturating_duration_since(Instant::now());
+            let mut events = Vec::new();
+            if event::poll(timeout)? {
+                events.push(event::read()?);
+                while event::poll(Duration::ZERO)? {
+                    events.push(event::read()?);
+                }
+            }
+            for event in events {
+                let key = match event {
+                    Event::Key(key) => key,
+                    Event::Resize(..) => {
+                        redraw = true;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let pressed = key.kind != KeyEventKind::Release;
+                match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Char(c) => {
+                        let Some(keypad) = tui::keypad_key(c) else {
+                            continue;
+                        };
+                        if !pressed {
+                            held.release(&mut self.interpreter, keypad);
+                        } else if !self.controls.debugger.is_paused() {
+                            held.press(&mut self.interpreter, keypad);
+                        }
+                    }
+                    _ if key.kind != KeyEventKind::Press => (),
+                    KeyCode::F(n) => {
+                        let result = self.controls.function_key(
+                            n,
+                            &mut self.interpreter,
+                            &mut scheduler,
+                            &self.rom,
+                        );
+                        advance |= result.advance;
+                        redraw |= result.redraw;
+                    }
+                    _ => (),
+                }
+            }
+
+            // one frame while paused, without stopping at a breakpoint on the PC
+            let was_paused = self.controls.debugger.is_paused();
+            if advance {
+                self.controls.debugger.resume(&self.interpreter);
+            }
+            let started = Instant::now();
+            let due = scheduler.frames_due(started);
+            let frames = if advance { 1 } else { due };
+            for _ in 0..frames {
+                if self.controls.debugger.is_paused()
+                    || (scheduler.speed() == Speed::Unlimited
+                        && started.elapsed() >= unlimited_slice)
+                {
+                    break;
+                }
+                if let Err(err) = self
+                    .interpreter
+                    .update(&[], &mut [&mut self.controls.debugger])
+                {
+                    self.controls.status = err.to_string();
+                    self.controls.debugger.pause();
+                }
+                held.end_frame(&mut self.interpreter);
+            }
+            if advance && !self.controls.debugger.is_paused() {
+                self.controls.debugger.pause();
+            }
+            // the debugger prints where it stopped, which is drawn over
+            if self.controls.debugger.is_paused() != was_paused {
+                redraw = true;
+            }
+
+            if redraw {
+                queue!(out, Clear(ClearType::All))?;
+                last.clear();
+            }
+            let mut frame = Vec::new();
+            self.draw(&mut frame, &scheduler)?;
+            if frame != last {
+                out.write_all(&frame)?;
+                out.flush()?;
+                last = frame;
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write, scheduler: &Scheduler) -> io::Result<()> {
+        let width = WIDTH as u16;
+        let rows = tui::ROWS as u16;
+        let border = "─".repeat(WIDTH);
+        queue!(out, MoveTo(0, 0), Print(format!("┌{border}┐")))?;
+        for y in 1..=rows {
+            queue!(
+                out,
+                MoveTo(0, y),
+                Print('│'),
+                MoveTo(width + 1, y),
+                Print('│')
+            )?;
+        }
+        queue!(out, MoveTo(0, rows + 1), Print(format!("└{border}┘")))?;
+
+        if self.mono {
+            for (y, line) in tui::half_blocks(&self.interpreter.screen)
+                .iter()
+                .enumerate()
+            {
+                queue!(out, MoveTo(1, 1 + y as u16), Print(line))?;
+            }
+        } else {
+            tui::draw_screen(out, &self.interpreter, 1, 1)?;
+        }
+
+        if self.controls.show_panel {
+            for (y, line) in tui::panel(&self.interpreter).iter().enumerate() {
+                queue!(out, MoveTo(PANEL_X, y as u16), Print(format!("{line:<26}")))?;
+            }
+        }
+
+        let state = if self.controls.debugger.is_paused() {
+            "PAUSED"
+        } else {
+            scheduler.speed().label()
+        };
+        let status = format!("{state:<7}{}", self.controls.status);
+        queue!(
+            out,
+            MoveTo(0, STATUS_Y),
+            Print(format!("{status:<width$}", width = WIDTH + 2))
+        )
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::filter::Filter;
+use crate::palette::Palette;
+use crate::phosphor::Persistence;
+
+/// Settings read from a config file, for every ROM and for ROMs by file name.
+///
+/// ```text
+/// # for every ROM
+/// palette = green
+/// persistence = decay:0.6
+/// filter = scanlines,rounded
+///
+/// [pong.ch8]
+/// palette = 000000,ffffff
+/// ```
+#[derive(Default)]
+pub struct Config {
+    defaults: Settings,
+    roms: HashMap<String, Settings>,
+}
+
+#[derive(Default)]
+struct Settings {
+    palette: Option<Palette>,
+    persistence: Option<Persistence>,
+    filters: Option<Vec<Filter>>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut rom: Option<String> = None;
+        for (i, line) in text.lines().enumerate() {
+            // colors can start with '#', so only whole lines are comments
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                rom = Some(name.trim().to_string());
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", i + 1));
+            };
+            let value = value.trim();
+            let settings = match &rom {
+                Some(name) => config.roms.entry(name.clone()).or_default(),
+                None => &mut config.defaults,
+            };
+            let bad = |what: &str| format!("line {}: bad {what} '{value}'", i + 1);
+            match key.trim() {
+                "palette" => {
+                    settings.palette = Some(Palette::parse(value).ok_or_else(|| bad("palette"))?)
+                }
+                "persistence" => {
+                    settings.persistence =
+                        Some(Persistence::parse(value).ok_or_else(|| bad("persistence"))?)
+                }
+                "filter" => {
+                    settings.filters = Some(Filter::parse_list(value).ok_or_else(|| bad("filter"))?)
+                }
+                key => return Err(format!("line {}: unknown setting '{key}'", i + 1)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::parse(&text)
+    }
+
+    // settings of the section named like the ROM's file
+    fn rom(&self, rom: &str) -> Option<&Settings> {
+        let name = Path::new(rom).file_name()?.to_string_lossy();
+        self.roms.get(name.as_ref())
+    }
+
+    /// The palette for `rom`, falling back to the one for every ROM.
+    pub fn palette_for(&self, rom: &str) -> Option<Palette> {
+        self.rom(rom)
+            .and_then(|rom| rom.palette)
+            .or(self.defaults.palette)
+    }
+
+    /// The persistence for `rom`, falling back to the one for every ROM.
+    pub fn persistence_for(&self, rom: &str) -> Option<Persistence> {
+        self.rom(rom)
+            .and_then(|rom| rom.persistence)
+            .or(self.defaults.persistence)
+    }
+
+    /// The filters for `rom`, falling back to the ones for every ROM.
+    pub fn filters_for(&self, rom: &str) -> Option<Vec<Filter>> {
+        self.rom(rom)
+            .and_then(|rom| rom.filters.clone())
+            .or_else(|| self.defaults.filters.clone())
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::BTreeSet;
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::disasm::disassemble;
+use crate::hexview::MemoryView;
+use crate::symbols::SymbolMap;
+use crate::{Interpreter, Observer};
+
+const HELP: &str = "\
+commands:
+  break <label|addr>   set a breakpoint
+  delete <label|addr>  remove a breakpoint
+  continue             resume execution
+  pause                stop execution
+  step [n]             execute n instructions (default 1)
+  where                show the current instruction
+  backtrace            show the call stack
+  registers            show the registers and timers
+  memory [addr] [len]  show memory as hex, PC in yellow, I in cyan, changes in red
+  poke <addr> <byte>.. write bytes to memory while paused";
+
+/// Breakpoints and stepping, driven by commands typed on stdin.
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    pub symbols: SymbolMap,
+    paused: bool,
+    // breakpoint to ignore once after resuming from it
+    resumed_from: Option<usize>,
+    commands: Option<Receiver<String>>,
+    pub memory: MemoryView,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            symbols: SymbolMap::default(),
+            paused: false,
+            resumed_from: None,
+            commands: None,
+            memory: MemoryView::new(),
+        }
+    }
+
+    /// Start reading commands from stdin.
+    pub fn attach_console(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        self.commands = Some(receiver);
+        println!("debugger attached, type 'help' for commands");
+    }
+
+    pub fn load_symbols(&mut self, filename: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(filename).map_err(|err| err.to_string())?;
+        self.symbols = SymbolMap::parse(&text)?;
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Continue running, without stopping again at a breakpoint on the current instruction.
+    pub fn resume(&mut self, interpreter: &Interpreter) {
+        self.paused = false;
+        self.resumed_from = Some(interpreter.program_counter);
+    }
+
+    pub fn toggle_pause(&mut self, interpreter: &Interpreter) {
+        if self.paused {
+            self.resume(interpreter);
+        } else {
+            self.pause();
+            self.print_location(interpreter);
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Returns whether there was a breakpoint at `addr`.
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// The current instruction and then the call of every frame on the stack, innermost first.
+    pub fn backtrace(&self, interpreter: &Interpreter) -> Vec<String> {
+        let mut frames = vec![format!("#0 {}", self.describe(interpreter.program_counter))];
+        for (i, &ret) in interpreter.stack.iter().rev().enumerate() {
+            // the return address follows the call instruction
+            let call = ret.saturating_sub(2) as usize;
+            frames.push(format!("#{} {}", i + 1, self.describe(call)));
+        }
+        frames
+    }
+
+    /// Handle every command typed since the last call.
+    pub fn poll(&mut self, interpreter: &mut Interpreter) {
+        let Some(commands) = &self.commands else {
+            return;
+        };
+        let lines: Vec<String> = commands.try_iter().collect();
+        for line in lines {
+            if let Err(err) = self.command(&line, interpreter) {
+                println!("{err}");
+            }
+        }
+    }
+
+    /// Run one console command, printing what it shows.
+    pub fn command(&mut self, line: &str, interpreter: &mut Interpreter) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => (),
+            ["help" | "h"] => println!("{HELP}"),
+            ["break" | "b", target] => {
+                let addr = self.resolve(target)?;
+                self.add_breakpoint(addr);
+                println!("breakpoint at {}", self.describe(addr));
+            }
+            ["delete" | "d", target] => {
+                let addr = self.resolve(target)?;
+                if !self.remove_breakpoint(addr) {
+                    return Err(format!("no breakpoint at {}", self.describe(addr)));
+                }
+            }
+            ["continue" | "c"] => self.resume(interpreter),
+            ["pause" | "p"] => {
+                self.pause();
+                self.print_location(interpreter);
+            }
+            ["step" | "s", ref count @ ..] => {
+                let count = match count {
+                    [] => 1,
+                    [n] => n.parse().map_err(|_| format!("'{n}' is not a number"))?,
+                    _ => return Err("usage: step [n]".to_string()),
+                };
+                self.pause();
+                for _ in 0..count {
+                    interpreter.exe().map_err(|err| err.to_string())?;
+                }
+                self.print_location(interpreter);
+            }
+            ["where" | "w"] => self.print_location(interpreter),
+            ["backtrace" | "bt"] => {
+                for frame in self.backtrace(interpreter) {
+                    println!("{frame}");
+                }
+            }
+            ["registers" | "r"] => {
+                for (i, v) in interpreter.registers.iter().enumerate() {
+                    print!("v{i:x}={v:02x} ");
+                }
+                println!();
+                println!(
+                    "i={:03x} pc={:03x} sp={} dt={} st={}",
+                    interpreter.index,
+                    interpreter.program_counter,
+                    interpreter.stack.len(),
+                    interpreter.delay_timer,
+                    interpreter.sound_timer
+                );
+            }
+            ["memory" | "x", ref range @ ..] => {
+                let (start, len) = match range {
+                    [] => (interpreter.index as usize, 0x40),
+                    [addr] => (self.resolve(addr)?, 0x40),
+                    [addr, len] => (self.resolve(addr)?, parse_hex(len)?),
+                    _ => return Err("usage: memory [addr] [len]".to_string()),
+                };
+                print!("{}", self.memory.dump(interpreter, start, len));
+            }
+            ["poke", addr, ref bytes @ ..] if !bytes.is_empty() => {
+                if !self.paused {
+                    return Err("pause before writing to memory".to_string());
+                }
+                let addr = self.resolve(addr)?;
+                let bytes = bytes
+                    .iter()
+                    .map(|b| {
+                        u8::from_str_radix(b.trim_start_matches("0x"), 16)
+                            .map_err(|_| format!("'{b}' is not a byte"))
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                let end = addr.checked_add(bytes.len());
+                let Some(target) = end.and_then(|end| interpreter.memory.get_mut(addr..end)) else {
+                    return Err("write goes past the end of memory".to_string());
+                };
+                target.copy_from_slice(&bytes);
+                print!("{}", self.memory.dump(interpreter, addr, bytes.len()));
+            }
+            _ => {
+                return Err(format!(
+                    "unknown command '{line}', type 'help' for commands"
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, target: &str) -> Result<usize, String> {
+        if let Some(addr) = self.symbols.address(target) {
+            return Ok(addr as usize);
+        }
+        parse_hex(target).map_err(|_| format!("unknown label or address '{target}'"))
+    }
+
+    fn describe(&self, addr: usize) -> String {
+        let mut text = format!("0x{addr:03x}");
+        if let Some(label) = self.symbols.describe(addr as u16) {
+            text += &format!(" {label}");
+        }
+        if let Some(location) = self.symbols.location(addr as u16) {
+            text += &format!(" ({location})");
+        }
+        text
+    }
+
+    fn print_location(&self, interpreter: &Interpreter) {
+        let pc = interpreter.program_counter;
+        match interpreter.read_opcode() {
+            Ok(opcode) => {
+                let text = disassemble(opcode).unwrap_or_else(|| "???".to_string());
+                println!("{}: {opcode:04x} {text}", self.describe(pc));
+            }
+            Err(err) => println!("{}: {err}", self.describe(pc)),
+        }
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Observer for Debugger {
+    fn before_exe(&mut self, interpreter: &Interpreter) -> bool {
+        if self.paused {
+            return true;
+        }
+
+        let pc = interpreter.program_counter;
+        if self.resumed_from.take() == Some(pc) || !self.breakpoints.contains(&pc) {
+            return false;
+        }
+
+        self.paused = true;
+        println!("breakpoint hit");
+        self.print_location(interpreter);
+        true
+    }
+}
+
+fn parse_hex(text: &str) -> Result<usize, String> {
+    usize::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("'{text}' is not a hex number"))
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::OFFSET;
+
+/// Disassemble a single opcode into Octo syntax.
+/// Returns `None` for opcodes `Interpreter::exe` does not support.
+pub fn disassemble(opcode: u16) -> Option<String> {
+    disassemble_with(opcode, |addr| format!("0x{addr:03X}"))
+}
+
+fn disassemble_with(opcode: u16, addr: impl Fn(u16) -> String) -> Option<String> {
+    let c = ((opcode & 0xF000) >> 12) as u8;
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    let text = match (c, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => "clear".to_string(),
+        (0x0, 0x0, 0xE, 0xE) => "return".to_string(),
+        (0x1, ..) => format!("jump {}", addr(nnn)),
+        (0x2, ..) => format!(":call {}", addr(nnn)),
+        // Octo's `if ... then` executes the next instruction when the condition holds,
+        // so the skip conditions are inverted
+        (0x3, ..) => format!("if v{x:x} != 0x{nn:02X} then"),
+        (0x4, ..) => format!("if v{x:x} == 0x{nn:02X} then"),
+        (0x5, ..) => format!("if v{x:x} != v{y:x} then"),
+        (0x6, ..) => format!("v{x:x} := 0x{nn:02X}"),
+        (0x7, ..) => format!("v{x:x} += 0x{nn:02X}"),
+        (0x8, _, _, 0x0) => format!("v{x:x} := v{y:x}"),
+        (0x8, _, _, 0x1) => format!("v{x:x} |= v{y:x}"),
+        (0x8, _, _, 0x2) => format!("v{x:x} &= v{y:x}"),
+        (0x8, _, _, 0x3) => format!("v{x:x} ^= v{y:x}"),
+        (0x8, _, _, 0x4) => format!("v{x:x} += v{y:x}"),
+        (0x8, _, _, 0x5) => format!("v{x:x} -= v{y:x}"),
+        (0x8, _, _, 0x6) => format!("v{x:x} >>= v{y:x}"),
+        (0x8, _, _, 0x7) => format!("v{x:x} =- v{y:x}"),
+        (0x8, _, _, 0xE) => format!("v{x:x} <<= v{y:x}"),
+        (0x9, ..) => format!("if v{x:x} == v{y:x} then"),
+        (0xA, ..) => format!("i := {}", addr(nnn)),
+        (0xB, ..) => format!("jump0 {}", addr(nnn)),
+        (0xC, ..) => format!("v{x:x} := random 0x{nn:02X}"),
+        (0xD, ..) => format!("sprite v{x:x} v{y:x} {n}"),
+        (0xE, _, 0x9, 0xE) => format!("if v{x:x} -key then"),
+        (0xE, _, 0xA, 0x1) => format!("if v{x:x} key then"),
+        (0xF, _, 0x0, 0x7) => format!("v{x:x} := delay"),
+        (0xF, _, 0x0, 0xA) => format!("v{x:x} := key"),
+        (0xF, _, 0x1, 0x5) => format!("delay := v{x:x}"),
+        (0xF, _, 0x1, 0x8) => format!("buzzer := v{x:x}"),
+        (0xF, _, 0x1, 0xE) => format!("i += v{x:x}"),
+        (0xF, _, 0x2, 0x9) => format!("i := hex v{x:x}"),
+        (0xF, _, 0x3, 0x3) => format!("bcd v{x:x}"),
+        (0xF, _, 0x5, 0x5) => format!("save v{x:x}"),
+        (0xF, _, 0x6, 0x5) => format!("load v{x:x}"),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// How an instruction passes control to the ones that follow it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    /// Falls through to the next instruction
+    Next,
+    /// Either falls through or skips the next instruction
+    Skip,
+    /// `1NNN`
+    Jump(usize),
+    /// `2NNN`, returns to the next instruction
+    Call(usize),
+    /// `00EE`
+    Return,
+    /// `BNNN`, the target depends on V0 at runtime
+    ComputedJump(usize),
+    /// Not an instruction `Interpreter::exe` can run
+    Invalid,
+}
+
+pub fn flow(opcode: u16) -> Flow {
+    if disassemble(opcode).is_none() {
+        return Flow::Invalid;
+    }
+
+    let nnn = (opcode & 0x0FFF) as usize;
+    match opcode >> 12 {
+        0x0 if opcode == 0x00EE => Flow::Return,
+        0x1 => Flow::Jump(nnn),
+        0x2 => Flow::Call(nnn),
+        0x3 | 0x4 | 0x5 | 0x9 | 0xE => Flow::Skip,
+        0xB => Flow::ComputedJump(nnn),
+        _ => Flow::Next,
+    }
+}
+
+/// Result of tracing control flow through a ROM loaded at `OFFSET`.
+pub struct Analysis {
+    rom: Vec<u8>,
+    // instruction start addresses, relative to OFFSET
+    code: BTreeSet<usize>,
+    labels: BTreeMap<usize, String>,
+    computed_jumps: BTreeSet<usize>,
+}
+
+impl Analysis {
+    /// Follow every path reachable from `OFFSET`: jumps, calls, both sides of skips,
+    /// stopping at returns and at opcodes `exe` would reject.
+    /// Everything not reached is treated as data.
+    pub fn trace(rom: &[u8]) -> Self {
+        let mut analysis = Self {
+            rom: rom.to_vec(),
+            code: BTreeSet::new(),
+            labels: BTreeMap::new(),
+            computed_jumps: BTreeSet::new(),
+        };
+
+        let mut pending = vec![OFFSET];
+        let mut called = BTreeSet::new();
+        let mut jumped = BTreeSet::new();
+        let mut referenced = BTreeSet::new();
+
+        while let Some(addr) = pending.pop() {
+            let Some(opcode) = analysis.opcode_at(addr) else {
+                continue;
+            };
+            if analysis.code.contains(&(addr - OFFSET)) {
+                continue;
+            }
+
+            let next = addr + 2;
+            match flow(opcode) {
+                Flow::Invalid => continue,
+                Flow::Next => pending.push(next),
+                Flow::Skip => pending.extend([next, next + 2]),
+                Flow::Jump(target) => {
+                    jumped.insert(target);
+                    pending.push(target);
+                }
+                Flow::Call(target) => {
+                    called.insert(target);
+                    pending.extend([target, next]);
+                }
+                Flow::Return => (),
+                Flow::ComputedJump(base) => {
+                    analysis.computed_jumps.insert(addr);
+                    referenced.insert(base);
+                }
+            }
+            if opcode >> 12 == 0xA {
+                referenced.insert((opcode & 0x0FFF) as usize);
+            }
+
+            analysis.code.insert(addr - OFFSET);
+        }
+
+        // entry point first so the listing starts with it
+        analysis.labels.insert(OFFSET, "main".to_string());
+        for target in called {
+            analysis.add_label(target, "sub");
+        }
+        for target in jumped {
+            analysis.add_label(target, "label");
+        }
+        for target in referenced {
+            analysis.add_label(target, "data");
+        }
+
+        analysis
+    }
+
+    fn opcode_at(&self, addr: usize) -> Option<u16> {
+        let i = addr.checked_sub(OFFSET)?;
+        let bytes = self.rom.get(i..i + 2)?;
+        Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+    }
+
+    fn add_label(&mut self, addr: usize, prefix: &str) {
+        if (OFFSET..OFFSET + self.rom.len()).contains(&addr) {
+            self.labels
+                .entry(addr)
+                .or_insert_with(|| format!("{prefix}_{addr:03x}"));
+        }
+    }
+
+    /// Addresses of `BNNN` instructions, whose targets cannot be traced statically.
+    pub fn computed_jumps(&self) -> impl Iterator<Item = usize> + '_ {
+        self.computed_jumps.iter().copied()
+    }
+
+    /// Octo source that assembles back into the same bytes.
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        let mut data_row = Vec::new();
+        let mut i = 0;
+
+        while i < self.rom.len() {
+            let addr = OFFSET + i;
+            if let Some(label) = self.labels.get(&addr) {
+                flush_data(&mut out, &mut data_row);
+                writeln!(out, ": {label}").unwrap();
+            }
+
+            // an instruction overlapped by a label can't be emitted as a whole
+            let emit_code = self.code.contains(&i) && !self.labels.contains_key(&(addr + 1));
+            if !emit_code {
+                data_row.push(self.rom[i]);
+                if data_row.len() == 8 {
+                    flush_data(&mut out, &mut data_row);
+                }
+                i += 1;
+                continue;
+            }
+
+            flush_data(&mut out, &mut data_row);
+            let opcode = self.opcode_at(addr).unwrap();
+            let text = disassemble_with(opcode, |target| {
+                self.labels
+                    .get(&(target as usize))
+                    .cloned()
+                    .unwrap_or_else(|| format!("0x{target:03X}"))
+            })
+            .unwrap();
+            let note = if self.computed_jumps.contains(&addr) {
+                " computed jump, targets not traced"
+            } else {
+                ""
+            };
+            if assembles_back(opcode) {
+                writeln!(out, "\t{text:<24} # {addr:03x}: {opcode:04x}{note}").unwrap();
+            } else {
+                let bytes = format!("0x{:02X} 0x{:02X}", opcode >> 8, opcode & 0xFF);
+                writeln!(out, "\t{bytes:<24} # {addr:03x}: {opcode:04x} {text}").unwrap();
+            }
+            i += 2;
+        }
+        flush_data(&mut out, &mut data_row);
+
+        out
+    }
+}
+
+// `exe` runs 5XYN and 9XYN whatever N is, but Octo only writes them with N = 0
+fn assembles_back(opcode: u16) -> bool {
+    !matches!(opcode >> 12, 0x5 | 0x9) || opcode & 0x000F == 0
+}
+
+fn flush_data(out: &mut String, row: &mut Vec<u8>) {
+    if row.is_empty() {
+        return;
+    }
+    let bytes: Vec<String> = row.iter().map(|b| format!("0x{b:02X}")).collect();
+    writeln!(out, "\t{}", bytes.join(" ")).unwrap();
+    row.clear();
+}
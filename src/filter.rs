@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+use crate::viewport;
+
+/// An effect on the scaled up screen. They all run on the CPU, so screenshots and recordings
+/// get them without a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Darkens the bottom half of every row of CHIP-8 pixels, like the gaps between the lines
+    /// of a CRT.
+    Scanlines,
+    /// Darkens the top and left edge of every CHIP-8 pixel.
+    Grid,
+    /// Cuts the corners off pixels where both neighbours at that corner have another color.
+    Rounded,
+    /// Smooths diagonal edges with Scale2x before scaling the rest of the way, twice for Scale4x.
+    Scale2x,
+}
+
+// brightness left in darkened pixels, out of 256
+const SCANLINE_SHADE: u32 = 160;
+const GRID_SHADE: u32 = 208;
+
+impl Filter {
+    pub const FILTERS: [(&'static str, Filter); 4] = [
+        ("scanlines", Filter::Scanlines),
+        ("grid", Filter::Grid),
+        ("rounded", Filter::Rounded),
+        ("scale2x", Filter::Scale2x),
+    ];
+
+    /// Parse a comma separated list of the names in `FILTERS`, or `none`.
+    pub fn parse_list(text: &str) -> Option<Vec<Filter>> {
+        if text == "none" {
+            return Some(Vec::new());
+        }
+        text.split(',')
+            .map(|name| {
+                Self::FILTERS
+                    .iter()
+                    .find(|(filter, _)| *filter == name.trim())
+                    .map(|&(_, filter)| filter)
+            })
+            .collect()
+    }
+}
+
+// where an output pixel falls in the CHIP-8 pixel it shows, along one axis
+#[derive(Clone, Copy)]
+struct Cell {
+    index: usize,
+    // of the output pixel's centre, from 0 to 1 across the CHIP-8 pixel
+    position: f32,
+    first: bool,
+}
+
+fn cells(from: usize, to: usize) -> Vec<Cell> {
+    (0..to)
+        .map(|x| Cell {
+            index: x * from / to,
+            position: ((2 * x + 1) * from % (2 * to)) as f32 / (2 * to) as f32,
+            first: x * from % to < from,
+        })
+        .collect()
+}
+
+/// Scale an RGBA frame up from `from` to `to` pixels like `viewport::scale_nearest`, running
+/// `filters` in order, except for Scale2x which runs before the scaling.
+pub fn render(
+    frame: &[u8],
+    from: (usize, usize),
+    out: &mut [u8],
+    to: (usize, usize),
+    filters: &[Filter],
+) {
+    let mut source = Cow::Borrowed(frame);
+    let mut size = from;
+    for _ in filters.iter().filter(|&&filter| filter == Filter::Scale2x) {
+        // no room for more detail
+        if size.0 * 2 > to.0 || size.1 * 2 > to.1 {
+            break;
+        }
+        source = Cow::Owned(scale2x(&source, size));
+        size = (size.0 * 2, size.1 * 2);
+    }
+    viewport::scale_nearest(&source, size, out, to);
+
+    let (columns, rows) = (cells(from.0, to.0), cells(from.1, to.1));
+    // lines only show when CHIP-8 pixels are at least two pixels large
+    let (wide, tall) = (to.0 >= from.0 * 2, to.1 >= from.1 * 2);
+    for filter in filters {
+        for (row, line) in rows.iter().zip(out.chunks_exact_mut(to.0 * 4)) {
+            for (column, pixel) in columns.iter().zip(line.chunks_exact_mut(4)) {
+                match filter {
+                    Filter::Scanlines if tall && row.position >= 0.5 => {
+                        shade(pixel, SCANLINE_SHADE)
+                    }
+                    Filter::Grid if (wide && column.first) || (tall && row.first) => {
+                        shade(pixel, GRID_SHADE)
+                    }
+                    Filter::Rounded => {
+                        if let Some(color) = corner(frame, from, *column, *row) {
+                            pixel.copy_from_slice(color);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+fn shade(pixel: &mut [u8], shade: u32) {
+    for channel in &mut pixel[..3] {
+        *channel = (*channel as u32 * shade / 256) as u8;
+    }
+}
+
+// the color around a rounded corner, for output pixels outside the circle in the CHIP-8 pixel
+fn corner(frame: &[u8], from: (usize, usize), column: Cell, row: Cell) -> Option<&[u8]> {
+    let (dx, dy) = (column.position - 0.5, row.position - 0.5);
+    if dx * dx + dy * dy <= 0.25 {
+        return None;
+    }
+    let x = column.index.checked_add_signed(dx.signum() as isize)?;
+    let y = row.index.checked_add_signed(dy.signum() as isize)?;
+    if x >= from.0 || y >= from.1 {
+        return None;
+    }
+    let pixel = |x: usize, y: usize| &frame[(y * from.0 + x) * 4..][..4];
+    let (beside, above) = (pixel(x, row.index), pixel(column.index, y));
+    (beside == above && beside != pixel(column.index, row.index)).then_some(beside)
+}
+
+// Scale2x, also known as AdvMAME2x: every pixel becomes four, each taking the color of its two
+// neighbours when they match and the other two neighbours don't
+fn scale2x(frame: &[u8], (width, height): (usize, usize)) -> Vec<u8> {
+    let pixel = |x: usize, y: usize| &frame[(y * width + x) * 4..][..4];
+    let mut out = vec![0; frame.len() * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let e = pixel(x, y);
+            let b = pixel(x, y.saturating_sub(1));
+            let h = pixel(x, (y + 1).min(height - 1));
+            let d = pixel(x.saturating_sub(1), y);
+            let f = pixel((x + 1).min(width - 1), y);
+            let quarters = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 4]
+            };
+            for (i, quarter) in quarters.iter().enumerate() {
+                let (x, y) = (x * 2 + i % 2, y * 2 + i / 2);
+                out[(y * width * 2 + x) * 4..][..4].copy_from_slice(quarter);
+            }
+        }
+    }
+    out
+}
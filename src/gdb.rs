@@ -0,0 +1,428 @@
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::debugger::Debugger;
+use crate::{Interpreter, STACK_SIZE};
+
+/// Register layout of the `g` packet, described to GDB by `TARGET_XML`:
+/// V0-VF, I and PC (little endian), SP, DT, ST.
+const REGISTER_SIZES: [usize; 21] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 1, 1, 1,
+];
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// GDB remote serial protocol server, controlling execution through the `Debugger`.
+pub struct GdbStub {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    input: Vec<u8>,
+    // a continue is in progress, GDB is waiting for the next stop
+    running: bool,
+}
+
+impl GdbStub {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        // port 0 picks a free one
+        let port = listener.local_addr()?.port();
+        println!("waiting for gdb on 127.0.0.1:{port}");
+        Ok(Self {
+            listener,
+            client: None,
+            input: vec![],
+            running: false,
+        })
+    }
+
+    /// The port GDB connects to.
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().map_or(0, |addr| addr.port())
+    }
+
+    /// Accept a connection, handle the packets received since the last call
+    /// and report a breakpoint hit to GDB.
+    pub fn poll(&mut self, interpreter: &mut Interpreter, debugger: &mut Debugger) {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    println!("gdb connected from {addr}");
+                    // replies go out in several small writes, don't let them wait on each other
+                    if stream.set_nonblocking(true).is_err() || stream.set_nodelay(true).is_err() {
+                        return;
+                    }
+                    // GDB expects the target to be stopped once attached
+                    debugger.pause();
+                    self.client = Some(stream);
+                    self.input.clear();
+                    self.running = false;
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    eprintln!("gdb accept error: {err}");
+                    return;
+                }
+            }
+        }
+
+        if let Err(err) = self.receive() {
+            println!("gdb disconnected: {err}");
+            self.client = None;
+            debugger.resume(interpreter);
+            return;
+        }
+
+        while let Some(packet) = self.next_packet() {
+            let reply = match packet {
+                Packet::Interrupt => {
+                    debugger.pause();
+                    self.running = false;
+                    Some("S02".to_string())
+                }
+                Packet::Command(command) => self.handle(&command, interpreter, debugger),
+            };
+            if let Some(reply) = reply {
+                self.send(&reply);
+            }
+        }
+
+        if self.running && debugger.is_paused() {
+            self.running = false;
+            self.send("S05");
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        let Some(client) = &mut self.client else {
+            return Ok(());
+        };
+        let mut buffer = [0; 1024];
+        loop {
+            match client.read(&mut buffer) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "connection closed",
+                    ))
+                }
+                Ok(n) => self.input.extend_from_slice(&buffer[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // split `$<data>#<checksum>` packets and interrupts out of the input, acknowledging each packet
+    fn next_packet(&mut self) -> Option<Packet> {
+        loop {
+            match *self.input.first()? {
+                0x03 => {
+                    self.input.remove(0);
+                    return Some(Packet::Interrupt);
+                }
+                b'$' => break,
+                // acknowledgements and noise
+                _ => {
+                    self.input.remove(0);
+                }
+            }
+        }
+
+        let end = self.input.iter().position(|&b| b == b'#')?;
+        if self.input.len() < end + 3 {
+            return None;
+        }
+        let data: Vec<u8> = self.input[1..end].to_vec();
+        let checksum = std::str::from_utf8(&self.input[end + 1..end + 3])
+            .ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        self.input.drain(..end + 3);
+
+        let valid = checksum == Some(data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+        self.write(if valid { b"+" } else { b"-" });
+        if !valid {
+            return self.next_packet();
+        }
+        Some(Packet::Command(String::from_utf8_lossy(&data).into_owned()))
+    }
+
+    fn send(&mut self, data: &str) {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        self.write(format!("${data}#{checksum:02x}").as_bytes());
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if let Some(client) = &mut self.client {
+            // the socket is non-blocking, but replies are small enough to go out at once
+            if let Err(err) = client.write_all(bytes) {
+                eprintln!("gdb write error: {err}");
+            }
+        }
+    }
+
+    // reply to a command, `None` when the reply comes later
+    fn handle(
+        &mut self,
+        command: &str,
+        interpreter: &mut Interpreter,
+        debugger: &mut Debugger,
+    ) -> Option<String> {
+        let reply = match command.as_bytes().first() {
+            Some(b'?') => "S05".to_string(),
+            Some(b'g') => {
+                let mut out = String::new();
+                for i in 0..REGISTER_SIZES.len() {
+                    out += &encode_register(i, read_register(interpreter, i));
+                }
+                out
+            }
+            Some(b'G') => {
+                let mut hex = &command[1..];
+                for (i, &size) in REGISTER_SIZES.iter().enumerate() {
+                    let Some(value) = hex.get(..size * 2).and_then(decode_le) else {
+                        return Some("E01".to_string());
+                    };
+                    write_register(interpreter, i, value);
+                    hex = &hex[size * 2..];
+                }
+                "OK".to_string()
+            }
+            Some(b'p') => match usize::from_str_radix(&command[1..], 16) {
+                Ok(i) if i < REGISTER_SIZES.len() => {
+                    encode_register(i, read_register(interpreter, i))
+                }
+                _ => "E01".to_string(),
+            },
+            Some(b'P') => {
+                let parsed = command[1..].split_once('=').and_then(|(reg, value)| {
+                    let i = usize::from_str_radix(reg, 16).ok()?;
+                    (i < REGISTER_SIZES.len()).then_some((i, decode_le(value)?))
+                });
+                match parsed {
+                    Some((i, value)) => {
+                        write_register(interpreter, i, value);
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            Some(b'm') => match parse_range(&command[1..]) {
+                Some((addr, len)) => {
+                    match memory_range(addr, len).and_then(|range| interpreter.memory.get(range)) {
+                        Some(bytes) => bytes.iter().map(|b| format!("{b:02x}")).collect(),
+                        None => "E01".to_string(),
+                    }
+                }
+                None => "E01".to_string(),
+            },
+            Some(b'M') => {
+                let write = command[1..].split_once(':').and_then(|(range, hex)| {
+                    let (addr, len) = parse_range(range)?;
+                    let bytes = decode_hex(hex)?;
+                    (bytes.len() == len).then_some((addr, bytes))
+                });
+                match write {
+                    Some((addr, bytes)) => {
+                        match memory_range(addr, bytes.len())
+                            .and_then(|range| interpreter.memory.get_mut(range))
+                        {
+                            Some(target) => {
+                                target.copy_from_slice(&bytes);
+                                "OK".to_string()
+                            }
+                            None => "E01".to_string(),
+                        }
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            Some(b'Z' | b'z') => {
+                // software and hardware breakpoints are the same thing here
+                let mut fields = command[1..].split(',');
+                let kind = fields.next();
+                let addr = fields
+                    .next()
+                    .and_then(|a| usize::from_str_radix(a, 16).ok());
+                match (kind, addr) {
+                    (Some("0" | "1"), Some(addr)) => {
+                        if command.starts_with('Z') {
+                            debugger.add_breakpoint(addr);
+                        } else {
+                            debugger.remove_breakpoint(addr);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            Some(b'c') => {
+                if let Some(addr) = parse_resume_addr(command) {
+                    interpreter.program_counter = addr;
+                }
+                debugger.resume(interpreter);
+                self.running = true;
+                return None;
+            }
+            Some(b's') => {
+                if let Some(addr) = parse_resume_addr(command) {
+                    interpreter.program_counter = addr;
+                }
+                match interpreter.exe() {
+                    Ok(()) => "S05".to_string(),
+                    // stopped by a fault
+                    Err(_) => "S0b".to_string(),
+                }
+            }
+            Some(b'D') => {
+                self.send("OK");
+                self.client = None;
+                debugger.resume(interpreter);
+                return None;
+            }
+            Some(b'k') => {
+                self.client = None;
+                debugger.resume(interpreter);
+                return None;
+            }
+            Some(b'H') => "OK".to_string(),
+            _ => self.query(command),
+        };
+        Some(reply)
+    }
+
+    fn query(&self, command: &str) -> String {
+        if command.starts_with("qSupported") {
+            "PacketSize=1000;qXfer:features:read+".to_string()
+        } else if let Some(args) = command.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, end)) = parse_range(args)
+                .and_then(|(offset, len)| Some((offset, offset.checked_add(len)?)))
+            else {
+                return "E01".to_string();
+            };
+            let start = offset.min(TARGET_XML.len());
+            let end = end.min(TARGET_XML.len());
+            let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+            format!("{marker}{}", &TARGET_XML[start..end])
+        } else {
+            match command {
+                "qAttached" => "1".to_string(),
+                "qC" => "QC1".to_string(),
+                "qfThreadInfo" => "m1".to_string(),
+                "qsThreadInfo" => "l".to_string(),
+                // unsupported, GDB falls back on something else
+                _ => String::new(),
+            }
+        }
+    }
+}
+
+enum Packet {
+    // Ctrl-C
+    Interrupt,
+    Command(String),
+}
+
+fn read_register(interpreter: &Interpreter, i: usize) -> u16 {
+    match i {
+        0..=15 => interpreter.registers[i] as u16,
+        16 => interpreter.index,
+        17 => interpreter.program_counter as u16,
+        18 => interpreter.stack.len() as u16,
+        19 => interpreter.delay_timer as u16,
+        _ => interpreter.sound_timer as u16,
+    }
+}
+
+fn write_register(interpreter: &mut Interpreter, i: usize, value: u16) {
+    match i {
+        0..=15 => interpreter.registers[i] = value as u8,
+        16 => interpreter.index = value,
+        17 => interpreter.program_counter = value as usize & 0xFFF,
+        // return addresses below the new stack pointer are kept, new ones are zero
+        18 => interpreter
+            .stack
+            .resize((value as usize).min(STACK_SIZE), 0),
+        19 => interpreter.delay_timer = value as u8,
+        _ => interpreter.sound_timer = value as u8,
+    }
+}
+
+fn encode_register(i: usize, value: u16) -> String {
+    let bytes = value.to_le_bytes();
+    let mut out = String::new();
+    for byte in &bytes[..REGISTER_SIZES[i]] {
+        write!(out, "{byte:02x}").unwrap();
+    }
+    out
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn decode_le(hex: &str) -> Option<u16> {
+    let bytes = decode_hex(hex)?;
+    if bytes.is_empty() || bytes.len() > 2 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &b| value << 8 | b as u16),
+    )
+}
+
+// `addr,len` in hex
+fn parse_range(text: &str) -> Option<(usize, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+// `len` bytes from `addr`, `None` when the client sent a range that overflows
+fn memory_range(addr: usize, len: usize) -> Option<std::ops::Range<usize>> {
+    Some(addr..addr.checked_add(len)?)
+}
+
+// optional address in `c addr` and `s addr`
+fn parse_resume_addr(command: &str) -> Option<usize> {
+    usize::from_str_radix(&command[1..], 16).ok()
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{screen, ExeError, Interpreter, KeypadKey, Observer};
+
+/// A key held down for `frames` frames from `frame` on.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyPress {
+    pub frame: u64,
+    pub key: KeypadKey,
+    pub frames: u64,
+}
+
+impl KeyPress {
+    /// Parse `frame:key[:frames]`, the key in hex and held for one frame unless given.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split(':');
+        let frame = parts.next()?.parse().ok()?;
+        let key = u8::from_str_radix(parts.next()?, 16)
+            .ok()
+            .and_then(KeypadKey::from_digit)?;
+        let frames = match parts.next() {
+            Some(frames) => frames.parse().ok()?,
+            None => 1,
+        };
+        parts
+            .next()
+            .is_none()
+            .then_some(Self { frame, key, frames })
+    }
+}
+
+/// Ends a run when the PC reaches an address or the program halts.
+#[derive(Default)]
+pub struct StopCondition {
+    pub pc: Option<usize>,
+    /// Stop at a jump to the jump itself, the usual way for programs to halt.
+    pub halt: bool,
+    pub reached: bool,
+}
+
+impl StopCondition {
+    pub fn is_set(&self) -> bool {
+        self.pc.is_some() || self.halt
+    }
+}
+
+impl Observer for StopCondition {
+    fn before_exe(&mut self, interpreter: &Interpreter) -> bool {
+        let pc = interpreter.program_counter;
+        let halted = self.halt
+            && interpreter
+                .memory
+                .get(pc..pc + 2)
+                .is_some_and(|b| ((b[0] as usize) << 8 | b[1] as usize) == (0x1000 | pc));
+        self.reached |= self.pc == Some(pc) || halted;
+        self.reached
+    }
+}
+
+/// Run for at most `frames` frames without a window, pressing keys as scripted,
+/// and return how many frames ran.
+pub fn run(
+    interpreter: &mut Interpreter,
+    frames: u64,
+    presses: &[KeyPress],
+    stop: &mut StopCondition,
+) -> (u64, Result<(), ExeError>) {
+    run_with(interpreter, frames, presses, stop, &mut [])
+}
+
+/// `run`, with more observers of the interpreter, like a `record::Recorder`.
+pub fn run_with(
+    interpreter: &mut Interpreter,
+    frames: u64,
+    presses: &[KeyPress],
+    stop: &mut StopCondition,
+    observers: &mut [&mut dyn Observer],
+) -> (u64, Result<(), ExeError>) {
+    let mut frame = 0;
+    while frame < frames && !stop.reached {
+        for press in presses {
+            if press.frame == frame {
+                interpreter.press_key(press.key);
+            } else if press.frame + press.frames == frame {
+                interpreter.release_key(press.key);
+            }
+        }
+        let mut all: Vec<&mut dyn Observer> = vec![&mut *stop];
+        for observer in observers.iter_mut() {
+            all.push(&mut **observer);
+        }
+        if let Err(err) = interpreter.update(&[], &mut all) {
+            return (frame, Err(err));
+        }
+        frame += 1;
+    }
+    (frame, Ok(()))
+}
+
+/// Save a PNG of the screen in `dir`, named after the ROM and the time, and return its path.
+pub fn screenshot(
+    interpreter: &Interpreter,
+    rom: &str,
+    dir: &Path,
+    scale: usize,
+) -> io::Result<PathBuf> {
+    let path = screen::capture_path(dir, rom, SystemTime::now(), "png");
+    screen::write_png(interpreter, &path, scale)?;
+    Ok(path)
+}
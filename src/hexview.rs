@@ -0,0 +1,141 @@
+use std::fmt::Write;
+
+use winit::keyboard::{Key, NamedKey};
+
+use crate::{Interpreter, OFFSET};
+
+/// Bytes per row of the hex view.
+pub const ROW_BYTES: usize = 16;
+/// Rows of the hex view shown in the overlay.
+pub const ROWS: usize = 16;
+
+const MEMORY_SIZE: usize = 4096;
+
+/// State of the memory hex view: which bytes changed during the last frame,
+/// the overlay's scroll position and the byte being edited.
+pub struct MemoryView {
+    previous: Vec<u8>,
+    changed: Vec<bool>,
+    pub cursor: usize,
+    // address of the first row in the overlay
+    pub top: usize,
+    // high nibble typed into the cursor byte so far
+    nibble: Option<u8>,
+}
+
+impl MemoryView {
+    pub fn new() -> Self {
+        Self {
+            previous: vec![0; MEMORY_SIZE],
+            changed: vec![false; MEMORY_SIZE],
+            cursor: OFFSET,
+            top: OFFSET,
+            nibble: None,
+        }
+    }
+
+    /// Compare `memory` to the previous frame's.
+    pub fn end_frame(&mut self, memory: &[u8]) {
+        for (i, (old, new)) in self.previous.iter_mut().zip(memory).enumerate() {
+            self.changed[i] = old != new;
+            *old = *new;
+        }
+    }
+
+    pub fn is_changed(&self, addr: usize) -> bool {
+        self.changed.get(addr).copied().unwrap_or(false)
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(delta)
+            .min(MEMORY_SIZE - 1);
+        self.nibble = None;
+
+        let row = self.cursor / ROW_BYTES * ROW_BYTES;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + ROWS * ROW_BYTES {
+            self.top = row + ROW_BYTES - ROWS * ROW_BYTES;
+        }
+    }
+
+    /// Navigate with the arrow and page keys; type hex digits into the cursor byte when `editable`.
+    pub fn handle_key(&mut self, key: &Key, memory: &mut [u8], editable: bool) {
+        match key {
+            Key::Named(NamedKey::ArrowLeft) => self.move_cursor(-1),
+            Key::Named(NamedKey::ArrowRight) => self.move_cursor(1),
+            Key::Named(NamedKey::ArrowUp) => self.move_cursor(-(ROW_BYTES as isize)),
+            Key::Named(NamedKey::ArrowDown) => self.move_cursor(ROW_BYTES as isize),
+            Key::Named(NamedKey::PageUp) => self.move_cursor(-((ROWS * ROW_BYTES) as isize)),
+            Key::Named(NamedKey::PageDown) => self.move_cursor((ROWS * ROW_BYTES) as isize),
+            _ if editable => {
+                let digit = key
+                    .to_text()
+                    .and_then(|text| u8::from_str_radix(text, 16).ok());
+                if let Some(digit) = digit {
+                    self.type_digit(digit, memory);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn type_digit(&mut self, digit: u8, memory: &mut [u8]) {
+        match self.nibble.take() {
+            None => self.nibble = Some(digit),
+            Some(high) => {
+                memory[self.cursor] = high << 4 | digit;
+                self.move_cursor(1);
+            }
+        }
+    }
+
+    /// Digit typed into the cursor byte that has not been written yet.
+    pub fn pending_nibble(&self) -> Option<u8> {
+        self.nibble
+    }
+
+    /// Rows of hex for the console, the PC in yellow, I in cyan and bytes changed last frame in red.
+    pub fn dump(&self, interpreter: &Interpreter, start: usize, len: usize) -> String {
+        let pc = interpreter.program_counter;
+        let index = interpreter.index as usize;
+        let end = start.saturating_add(len).min(MEMORY_SIZE);
+
+        let mut out = String::new();
+        let mut row = start / ROW_BYTES * ROW_BYTES;
+        while row < end {
+            write!(out, "{row:03x}:").unwrap();
+            for addr in row..row + ROW_BYTES {
+                if !(start..end).contains(&addr) {
+                    out += "   ";
+                    continue;
+                }
+                let byte = interpreter.memory[addr];
+                let color = if addr == pc || addr == pc + 1 {
+                    Some(33)
+                } else if addr == index {
+                    Some(36)
+                } else if self.is_changed(addr) {
+                    Some(31)
+                } else {
+                    None
+                };
+                match color {
+                    Some(color) => write!(out, " \x1b[{color}m{byte:02x}\x1b[0m").unwrap(),
+                    None => write!(out, " {byte:02x}").unwrap(),
+                }
+            }
+            out += "\n";
+            row += ROW_BYTES;
+        }
+        out
+    }
+}
+
+impl Default for MemoryView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
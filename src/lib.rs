@@ -0,0 +1,675 @@
+pub mod alu;
+pub mod asm;
+pub mod config;
+pub mod debugger;
+pub mod disasm;
+pub mod filter;
+pub mod font;
+pub mod gdb;
+pub mod headless;
+pub mod hexview;
+pub mod overlay;
+pub mod palette;
+pub mod phosphor;
+pub mod profile;
+pub mod quirks;
+pub mod record;
+pub mod scheduler;
+pub mod screen;
+pub mod state;
+pub mod symbols;
+pub mod timing;
+pub mod trace;
+pub mod tui;
+pub mod viewport;
+
+pub use filter::Filter;
+pub use palette::Palette;
+pub use phosphor::Persistence;
+pub use quirks::Quirks;
+pub use timing::Timing;
+
+use winit::{event::ElementState, keyboard::Key};
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+pub const OFFSET: usize = 0x200;
+pub const TARGET_FPS: u64 = 60;
+pub const IPF: usize = 500; // default instructions per frame
+/// Subroutine calls the stack holds, as many as SUPER-CHIP's.
+pub const STACK_SIZE: usize = 16;
+
+#[derive(Clone, Copy)]
+struct KeyState {
+    pressed_frames_ago: u8,
+    released_frames_ago: u8,
+    down: bool,
+}
+
+impl KeyState {
+    fn new() -> Self {
+        Self {
+            pressed_frames_ago: 60,
+            released_frames_ago: 60,
+            down: false,
+        }
+    }
+    fn press(&mut self) {
+        self.pressed_frames_ago = 0;
+        self.down = true;
+    }
+    fn release(&mut self) {
+        self.released_frames_ago = 0;
+        self.down = false;
+    }
+    fn update_pressed(&mut self) {
+        self.pressed_frames_ago = (self.pressed_frames_ago + 1).min(60);
+    }
+    fn update_released(&mut self) {
+        self.released_frames_ago = (self.released_frames_ago + 1).min(60);
+    }
+    fn is_pressed(&self) -> bool {
+        self.pressed_frames_ago == 1
+    }
+    fn is_down(&self) -> bool {
+        self.down
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum KeypadKey {
+    Key0 = 0x0,
+    Key1 = 0x1,
+    Key2 = 0x2,
+    Key3 = 0x3,
+    Key4 = 0x4,
+    Key5 = 0x5,
+    Key6 = 0x6,
+    Key7 = 0x7,
+    Key8 = 0x8,
+    Key9 = 0x9,
+    KeyA = 0xA,
+    KeyB = 0xB,
+    KeyC = 0xC,
+    KeyD = 0xD,
+    KeyE = 0xE,
+    KeyF = 0xF,
+}
+
+impl KeypadKey {
+    const ALL: [KeypadKey; 16] = [
+        KeypadKey::Key0,
+        KeypadKey::Key1,
+        KeypadKey::Key2,
+        KeypadKey::Key3,
+        KeypadKey::Key4,
+        KeypadKey::Key5,
+        KeypadKey::Key6,
+        KeypadKey::Key7,
+        KeypadKey::Key8,
+        KeypadKey::Key9,
+        KeypadKey::KeyA,
+        KeypadKey::KeyB,
+        KeypadKey::KeyC,
+        KeypadKey::KeyD,
+        KeypadKey::KeyE,
+        KeypadKey::KeyF,
+    ];
+
+    /// The key for a hex digit, `0x0` to `0xF`.
+    pub fn from_digit(digit: u8) -> Option<Self> {
+        Self::ALL.get(digit as usize).copied()
+    }
+}
+
+pub fn get_key(key: &str) -> Option<KeypadKey> {
+    // ╔═══╦═══╦═══╦═══╗       ╔═══╦═══╦═══╦═══╗
+    // ║ 1 ║ 2 ║ 3 ║ 4 ║       ║ 1 ║ 2 ║ 3 ║ C ║
+    // ╠═══╬═══╬═══╬═══╣       ╠═══╬═══╬═══╬═══╣
+    // ║ Q ║ W ║ E ║ R ║       ║ 4 ║ 5 ║ 6 ║ D ║
+    // ╠═══╬═══╬═══╬═══╣  -->  ╠═══╬═══╬═══╬═══╣
+    // ║ A ║ S ║ D ║ F ║       ║ 7 ║ 8 ║ 9 ║ E ║
+    // ╠═══╬═══╬═══╬═══╣       ╠═══╬═══╬═══╬═══╣
+    // ║ Z ║ X ║ C ║ V ║       ║ A ║ 0 ║ B ║ F ║
+    // ╚═══╩═══╩═══╩═══╝       ╚═══╩═══╩═══╩═══╝
+    match key {
+        "1" => Some(KeypadKey::Key1),
+        "2" => Some(KeypadKey::Key2),
+        "3" => Some(KeypadKey::Key3),
+        "4" => Some(KeypadKey::KeyC),
+
+        "q" /*| "Q"*/ => Some(KeypadKey::Key4),
+        "w" /*| "W"*/ => Some(KeypadKey::Key5),
+        "e" /*| "E"*/ => Some(KeypadKey::Key6),
+        "r" /*| "R"*/ => Some(KeypadKey::KeyD),
+
+        "a" /*| "A"*/ => Some(KeypadKey::Key7),
+        "s" /*| "S"*/ => Some(KeypadKey::Key8),
+        "d" /*| "D"*/ => Some(KeypadKey::Key9),
+        "f" /*| "F"*/ => Some(KeypadKey::KeyE),
+
+        "z" /*| "Z"*/ => Some(KeypadKey::KeyA),
+        "x" /*| "X"*/ => Some(KeypadKey::Key0),
+        "c" /*| "C"*/ => Some(KeypadKey::KeyB),
+        "v" /*| "V"*/ => Some(KeypadKey::KeyF),
+
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExeError {
+    UnknownOpcode { addr: usize, opcode: u16 },
+    StackUnderflow { addr: usize },
+    StackOverflow { addr: usize },
+    MemoryOutOfBounds { addr: usize, access: usize },
+}
+
+impl std::fmt::Display for ExeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnknownOpcode { addr, opcode } => {
+                write!(f, "unknown opcode {opcode:04x} at {addr:03x}")
+            }
+            Self::StackUnderflow { addr } => write!(f, "return with an empty stack at {addr:03x}"),
+            Self::StackOverflow { addr } => {
+                write!(f, "call with a full stack of {STACK_SIZE} at {addr:03x}")
+            }
+            Self::MemoryOutOfBounds { addr, access } => {
+                write!(
+                    f,
+                    "memory access at {access:04x} out of bounds at {addr:03x}"
+                )
+            }
+        }
+    }
+}
+
+/// Watches the instructions `Interpreter::update` executes.
+pub trait Observer {
+    /// Called before every instruction, returning `true` ends the frame early.
+    fn before_exe(&mut self, _interpreter: &Interpreter) -> bool {
+        false
+    }
+    /// Called after every instruction with the address it was executed from.
+    fn after_exe(
+        &mut self,
+        _interpreter: &Interpreter,
+        _addr: usize,
+        _result: Result<(), ExeError>,
+    ) {
+    }
+    fn end_frame(&mut self, _interpreter: &Interpreter) {}
+}
+
+enum KeyStatus {
+    NoKeyAwait,
+    KeyAwait,
+    KeyConf(KeypadKey),
+}
+
+pub struct Interpreter {
+    pub memory: Vec<u8>,
+    pub screen: screen::Screen,
+    pub program_counter: usize,
+    pub index: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub registers: [u8; 16],
+    pub quirks: Quirks,
+    pub timing: Timing,
+    pub palette: Palette,
+    pub persistence: Persistence,
+    /// Run on the screen after it's scaled up, in screenshots, recordings and the window.
+    pub filters: Vec<Filter>,
+    halt: bool,
+    keys: [KeyState; 16],
+    key_wait_status: KeyStatus,
+    // of every pixel from 0 to 1, faded every frame by `persistence`
+    brightness: Vec<f32>,
+    // set by `DXYN` with the display wait quirk, the rest of the frame is skipped
+    vblank_wait: bool,
+    // left over from earlier frames, negative when the last instruction overran them
+    budget: i64,
+    // the sound timer was running during the last frame, before it counted down
+    buzzing: bool,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            memory: vec![0; 4096],
+            screen: [[false; WIDTH]; HEIGHT],
+            program_counter: OFFSET,
+            index: 0,
+            stack: vec![],
+            delay_timer: 0,
+            sound_timer: 0,
+            registers: [0; 16],
+            quirks: Quirks::default(),
+            timing: Timing::default(),
+            palette: Palette::default(),
+            persistence: Persistence::default(),
+            filters: Vec::new(),
+            halt: false,
+            keys: [KeyState::new(); 16],
+            key_wait_status: KeyStatus::NoKeyAwait,
+            brightness: vec![0.0; WIDTH * HEIGHT],
+            vblank_wait: false,
+            budget: 0,
+            buzzing: false,
+        }
+    }
+
+    pub fn load(&mut self, filename: &str) -> Result<(), std::io::Error> {
+        let bytes = std::fs::read(filename)?;
+        if bytes.len() > self.memory.len() - OFFSET {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "the program is {} bytes, only {} fit in memory",
+                    bytes.len(),
+                    self.memory.len() - OFFSET
+                ),
+            ));
+        }
+        self.load_rom(&bytes);
+        Ok(())
+    }
+
+    /// A machine with the font loaded and `opcodes` as its program, registers and
+    /// everything else can then be set up through the fields before stepping with `exe`.
+    pub fn with_program(opcodes: &[u16]) -> Self {
+        let bytes: Vec<u8> = opcodes.iter().flat_map(|op| op.to_be_bytes()).collect();
+        let mut interpreter = Self::new();
+        interpreter.load_rom(&bytes);
+        interpreter
+    }
+
+    /// Load the font and `bytes` as the program, which must fit in memory after `OFFSET`.
+    pub fn load_rom(&mut self, bytes: &[u8]) {
+        // load font
+        self.memory[0..80].copy_from_slice(&[
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+            0x20, 0x60, 0x20, 0x20, 0x70, // 1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+        ]);
+
+        // load program
+        self.memory[OFFSET..OFFSET + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn read_opcode(&self) -> Result<u16, ExeError> {
+        let p = self.program_counter;
+        self.check_memory(p, p, 2)?;
+        let op_byte1 = self.memory[p] as u16;
+        let op_byte2 = self.memory[p + 1] as u16;
+
+        Ok(op_byte1 << 8 | op_byte2)
+    }
+
+    // `len` bytes from `start` must be inside memory for the instruction at `addr`
+    fn check_memory(&self, addr: usize, start: usize, len: usize) -> Result<(), ExeError> {
+        if start + len > self.memory.len() {
+            return Err(ExeError::MemoryOutOfBounds {
+                addr,
+                access: start + len - 1,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn exe(&mut self) -> Result<(), ExeError> {
+        if self.halt {
+            return Ok(());
+        }
+
+        let addr = self.program_counter;
+        let opcode = self.read_opcode()?;
+        self.program_counter += 2;
+
+        // println!("{:04x}", opcode);
+        // println!("{:?}", self.stack);
+
+        let c = ((opcode & 0xF000) >> 12) as u8;
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let n = (opcode & 0x000F) as u8;
+
+        let nn = opcode & 0x00FF;
+        let nnn = opcode & 0x0FFF;
+
+        match (c, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => {
+                //Clear the screen
+                for row in &mut self.screen {
+                    for pix in row {
+                        *pix = false;
+                    }
+                }
+            }
+            (0x0, 0x0, 0xE, 0xE) => {
+                //Return from a subroutine
+                let ret = self.stack.pop().ok_or(ExeError::StackUnderflow { addr })?;
+                self.program_counter = ret as usize;
+            }
+            (0x0, ..) => {
+                //TODO Execute machine language subroutine at address NNN
+                return Err(ExeError::UnknownOpcode { addr, opcode });
+            }
+            (0x1, ..) => {
+                //Jump to address NNN
+                self.program_counter = nnn as usize;
+            }
+            (0x2, ..) => {
+                // Execute subroutine starting at address NNN
+                if self.stack.len() >= STACK_SIZE {
+                    return Err(ExeError::StackOverflow { addr });
+                }
+                self.stack.push(self.program_counter as u16);
+                self.program_counter = nnn as usize;
+            }
+            (0x3, ..) => {
+                //Skip the following instruction if the value of register VX equals NN
+                if self.registers[x as usize] == nn as u8 {
+                    self.program_counter += 2;
+                }
+            }
+            (0x4, ..) => {
+                //Skip the following instruction if the value of register VX is not equal to NN
+                if self.registers[x as usize] != nn as u8 {
+                    self.program_counter += 2;
+                }
+            }
+            (0x5, ..) => {
+                //Skip the following instruction if the value of register VX is equal to the value of register VY
+                if self.registers[x as usize] == self.registers[y as usize] {
+                    self.program_counter += 2;
+                }
+            }
+            (0x6, ..) => {
+                //Store number NN in register VX
+                self.registers[x as usize] = nn as u8;
+            }
+            (0x7, ..) => {
+                //Add the value NN to register VX
+                self.registers[x as usize] = alu::add_value(self.registers[x as usize], nn as u8);
+            }
+            (0x8, ..) => {
+                // Arithmetic and logic between VX and VY, storing the result in VX and the flag in VF
+                let (vx, vy) = (self.registers[x as usize], self.registers[y as usize]);
+                let (value, flag) = alu::register_op(n, vx, vy, &self.quirks)
+                    .ok_or(ExeError::UnknownOpcode { addr, opcode })?;
+                self.registers[x as usize] = value;
+                if let Some(flag) = flag {
+                    self.registers[0xF] = flag;
+                }
+            }
+            (0x9, ..) => {
+                //Skip the following instruction if the value of register VX is not equal to the value of register VY
+                if self.registers[x as usize] != self.registers[y as usize] {
+                    self.program_counter += 2;
+                }
+            }
+            (0xA, ..) => {
+                self.index = nnn;
+            }
+            (0xB, ..) => {
+                //Jump to address NNN + V0, or NNN + VX with the jumping quirk
+                let offset = if self.quirks.jump_vx { x } else { 0 };
+                self.program_counter = nnn as usize + self.registers[offset as usize] as usize;
+            }
+            (0xC, ..) => {
+                //Set VX to a random number with a mask of NN
+                self.registers[x as usize] = rand::random::<u8>() & nn as u8;
+            }
+            (0xD, ..) => {
+                // Draw a sprite at position VX, VY with N bytes of sprite data starting at the address stored in I
+                // Set VF to 01 if any set pixels are changed to unset, and 00 otherwise
+                // the coordinates are read before VF is cleared, they can be in VF
+                let x = self.registers[x as usize] as usize % WIDTH;
+                let y = self.registers[y as usize] as usize % HEIGHT;
+                self.registers[0xF] = 0x00;
+
+                let clipped_n = (y + n as usize).min(HEIGHT) - y;
+                self.check_memory(addr, self.index as usize, clipped_n)?;
+
+                for i in 0..clipped_n {
+                    let byte = self.memory[self.index as usize + i];
+
+                    let bits: &mut [bool] = &mut self.screen[y + i][x..(x + 8).min(WIDTH)];
+                    for (i, bit) in bits.iter_mut().enumerate() {
+                        let new = (byte >> (7 - i)) % 2 == 1;
+                        if *bit && new {
+                            self.registers[0xF] = 0x01;
+                        }
+                        *bit ^= new;
+                    }
+                }
+                self.vblank_wait = self.quirks.display_wait;
+            }
+            (0xE, _, 0x9, 0xE) => {
+                //Skip the following instruction if the key corresponding to the hex value currently stored in register VX is pressed
+                if self.is_key_pressed(self.registers[x as usize]) {
+                    self.program_counter += 2;
+                }
+            }
+            (0xE, _, 0xA, 0x1) => {
+                //Skip the following instruction if the key corresponding to the hex value currently stored in register VX is not pressed
+                if !self.is_key_pressed(self.registers[x as usize]) {
+                    self.program_counter += 2;
+                }
+            }
+            (0xF, _, 0x0, 0x7) => {
+                //Store the current value of the delay timer in register VX
+                self.registers[x as usize] = self.delay_timer;
+            }
+            (0xF, _, 0x0, 0xA) => {
+                // Wait for a keypress and store the result in register VX
+                // On the original COSMAC VIP, the key was only registered when it was pressed and then released.
+                use KeyStatus as KS;
+                self.key_wait_status = match self.key_wait_status {
+                    KS::KeyConf(key) => {
+                        self.registers[x as usize] = key as u8;
+                        KS::NoKeyAwait
+                    }
+                    _ => {
+                        self.program_counter -= 2;
+                        KS::KeyAwait
+                    }
+                }
+            }
+            (0xF, _, 0x1, 0x5) => {
+                //Set the delay timer to the value of register VX
+                self.delay_timer = self.registers[x as usize];
+            }
+            (0xF, _, 0x1, 0x8) => {
+                //Set the sound timer to the value of register VX
+                self.sound_timer = self.registers[x as usize];
+            }
+            (0xF, _, 0x1, 0xE) => {
+                //Add the value stored in register VX to register I
+                let (index, flag) =
+                    alu::add_index(self.index, self.registers[x as usize], &self.quirks);
+                self.index = index;
+                if let Some(flag) = flag {
+                    self.registers[0xF] = flag;
+                }
+            }
+            (0xF, _, 0x2, 0x9) => {
+                //Set I to the memory address of the sprite data corresponding to the hexadecimal digit stored in register VX
+                self.index = self.registers[x as usize] as u16 * 5; // hardcoded in the load method
+            }
+            (0xF, _, 0x3, 0x3) => {
+                //Store the binary-coded decimal equivalent of the value stored in register VX at addresses I, I + 1, and I + 2
+                self.check_memory(addr, self.index as usize, 3)?;
+                self.memory[self.index as usize] = self.registers[x as usize] / 100;
+                self.memory[self.index as usize + 1] = (self.registers[x as usize] / 10) % 10;
+                self.memory[self.index as usize + 2] = self.registers[x as usize] % 10;
+            }
+            (0xF, _, 0x5, 0x5) => {
+                //Store the values of registers V0 to VX inclusive in memory starting at address I
+                //I is set to I + X + 1 after operation²
+                self.check_memory(addr, self.index as usize, x as usize + 1)?;
+                if self.quirks.memory_increment {
+                    for i in 0..=x as usize {
+                        self.memory[self.index as usize] = self.registers[i];
+                        self.index += 1;
+                    }
+                } else {
+                    for i in 0..=x as usize {
+                        self.memory[self.index as usize + i] = self.registers[i];
+                    }
+                }
+            }
+            (0xF, _, 0x6, 0x5) => {
+                //Fill registers V0 to VX inclusive with the values stored in memory starting at address I
+                //I is set to I + X + 1 after operation²
+                self.check_memory(addr, self.index as usize, x as usize + 1)?;
+                if self.quirks.memory_increment {
+                    for i in 0..=x as usize {
+                        self.registers[i] = self.memory[self.index as usize];
+                        self.index += 1;
+                    }
+                } else {
+                    for i in 0..=x as usize {
+                        self.registers[i] = self.memory[self.index as usize + i];
+                    }
+                }
+            }
+            _ => {
+                return Err(ExeError::UnknownOpcode { addr, opcode });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, frame: &mut [u8]) {
+        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&self.pixel_rgba(i % WIDTH, i / WIDTH));
+        }
+    }
+
+    /// The color of a pixel, between the background and foreground while it fades.
+    pub fn pixel_rgba(&self, x: usize, y: usize) -> [u8; 4] {
+        let lit = self.screen[y][x];
+        if self.persistence == Persistence::Off {
+            return self.palette.rgba(lit as usize);
+        }
+        let brightness = self.brightness[y * WIDTH + x];
+        let (off, on) = (self.palette.rgba(0), self.palette.rgba(1));
+        let mut rgba = [0xff; 4];
+        for c in 0..3 {
+            let mixed = off[c] as f32 + (on[c] as f32 - off[c] as f32) * brightness;
+            rgba[c] = mixed.round() as u8;
+        }
+        rgba
+    }
+
+    pub fn update(
+        &mut self,
+        keys: &[(Key, ElementState)],
+        observers: &mut [&mut dyn Observer],
+    ) -> Result<(), ExeError> {
+        for (key, state) in keys {
+            if let Some(key) = key.to_text().and_then(get_key) {
+                match state {
+                    ElementState::Pressed => self.press_key(key),
+                    ElementState::Released => self.release_key(key),
+                }
+                println!("{key:?} {state:?}");
+            }
+        }
+
+        self.budget += self.timing.frame_budget();
+        self.vblank_wait = false;
+        while self.budget > 0 {
+            if observers.iter_mut().any(|o| o.before_exe(self)) {
+                // a stopped frame doesn't hand what it didn't run to the next one
+                self.budget = 0;
+                break;
+            }
+            let addr = self.program_counter;
+            self.budget -= self.timing.cost(self.read_opcode().ok(), &self.registers);
+            let result = self.exe();
+            for observer in observers.iter_mut() {
+                observer.after_exe(self, addr, result);
+            }
+            result?;
+            if self.vblank_wait {
+                // the sprite is drawn, the next instruction runs after the vertical blank
+                self.budget = self.budget.min(0);
+                break;
+            }
+        }
+
+        self.buzzing = self.sound_timer > 0;
+        self.tick();
+
+        for observer in observers.iter_mut() {
+            observer.end_frame(self);
+        }
+        Ok(())
+    }
+
+    /// Whether the buzzer sounded during the last frame of `update`. The sound timer has already
+    /// counted down by then, a timer of 1 is down to 0 and still sounded for that frame.
+    pub fn buzzing(&self) -> bool {
+        self.buzzing
+    }
+
+    /// Count down the timers, age the key presses and fade the pixels by one frame.
+    pub fn tick(&mut self) {
+        for (i, brightness) in self.brightness.iter_mut().enumerate() {
+            let lit = self.screen[i / WIDTH][i % WIDTH];
+            *brightness = self.persistence.fade(*brightness, lit);
+        }
+
+        // update timers
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+
+        // update kets
+        for key in &mut self.keys {
+            key.update_pressed();
+            key.update_released();
+        }
+    }
+
+    pub fn press_key(&mut self, key: KeypadKey) {
+        self.keys[key as usize].press();
+    }
+
+    pub fn release_key(&mut self, key: KeypadKey) {
+        if let KeyStatus::KeyAwait = self.key_wait_status {
+            self.key_wait_status = KeyStatus::KeyConf(key);
+        }
+
+        self.keys[key as usize].release();
+    }
+
+    fn is_key_pressed(&self, key: u8) -> bool {
+        // only the low digit selects a key
+        self.keys[(key & 0xF) as usize].is_pressed()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
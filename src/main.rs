@@ -1,88 +1,217 @@
+use chip8::config::Config;
+use chip8::debugger::Debugger;
+use chip8::scheduler::{Scheduler, Speed};
+use chip8::viewport::{self, Scaling};
+use chip8::{asm, disasm, filter, gdb, headless, overlay, profile, record, screen, symbols, trace};
+use chip8::{Filter, Interpreter, Observer, Palette, Persistence, Quirks, Timing};
+use chip8::{HEIGHT, TARGET_FPS, WIDTH};
+
 use pixels::{Pixels, SurfaceTexture};
-use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use winit::{
     dpi::LogicalSize,
     event::{ElementState, Event, KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
 };
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
-const OFFSET: usize = 0x200;
-const TARGET_FPS: u64 = 60;
-const IPF: usize = 500; // instructions per frame
-const AMIGA_BEHAVIOUR: bool = false;
-const MODERN_STR_LD_BEHAVIOUR: bool = false;
-const MODERN_SHIFT_BEHAVIOUR: bool = false;
-const VF_RESET: bool = true;
-
-#[derive(Clone, Copy)]
-struct KeyState {
-    pressed_frames_ago: u8,
-    released_frames_ago: u8,
-}
+// read from the working directory when there is no --config
+const CONFIG: &str = "chip8.conf";
 
-impl KeyState {
-    fn new() -> Self {
-        Self {
-            pressed_frames_ago: 60,
-            released_frames_ago: 60,
+fn main() {
+    let mut rom = None;
+    let mut symbols = None;
+    let mut debug = false;
+    let mut gdb_port = None;
+    let mut trace_file = None;
+    let mut trace_ring = None;
+    let mut trace_filter = trace::TraceFilter::default();
+    let mut tracing = false;
+    let mut profile = None;
+    let mut quirks = Quirks::default();
+    let mut display_wait = false;
+    let mut timing = Timing::default();
+    let mut screenshot_scale = 1;
+    let mut window_scale = 10;
+    let mut scaling = Scaling::default();
+    let mut palette = None;
+    let mut persistence = None;
+    let mut filters = None;
+    let mut config_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--asm" => {
+                let (Some(source), Some(output)) = (args.next(), args.next()) else {
+                    eprintln!("usage: chip8 --asm <source.8o> <output.ch8>");
+                    std::process::exit(1);
+                };
+                assemble(&source, &output);
+                return;
+            }
+            "--disasm" => {
+                let Some(rom) = args.next() else {
+                    eprintln!("usage: chip8 --disasm <rom.ch8>");
+                    std::process::exit(1);
+                };
+                let bytes = std::fs::read(rom).unwrap();
+                let analysis = disasm::Analysis::trace(&bytes);
+                print!("{}", analysis.listing());
+                for addr in analysis.computed_jumps() {
+                    eprintln!("computed jump at {addr:03x}, its targets were not traced");
+                }
+                return;
+            }
+            "--debug" => debug = true,
+            "--quirks" => {
+                let Some(preset) = args.next().and_then(|name| Quirks::preset(&name)) else {
+                    eprintln!("usage: chip8 --quirks <chip8|superchip|xochip>");
+                    std::process::exit(1);
+                };
+                quirks = preset;
+            }
+            "--display-wait" => display_wait = true,
+            "--timing" => {
+                let Some(mode) = args.next().and_then(|mode| Timing::parse(&mode)) else {
+                    eprintln!("usage: chip8 --timing <ipf:N|ips:N|vip>");
+                    std::process::exit(1);
+                };
+                timing = mode;
+            }
+            "--palette" => {
+                let Some(spec) = args.next().and_then(|spec| Palette::parse(&spec)) else {
+                    eprintln!(
+                        "usage: chip8 --palette <mono|green|amber|octo|lcd|rrggbb,rrggbb[,rrggbb,rrggbb]>"
+                    );
+                    std::process::exit(1);
+                };
+                palette = Some(spec);
+            }
+            "--persistence" => {
+                let Some(mode) = args.next().and_then(|mode| Persistence::parse(&mode)) else {
+                    eprintln!("usage: chip8 --persistence <off|decay:S|blend:S>, S from 0 to 1");
+                    std::process::exit(1);
+                };
+                persistence = Some(mode);
+            }
+            "--filter" => {
+                let Some(list) = args.next().and_then(|list| Filter::parse_list(&list)) else {
+                    eprintln!("usage: chip8 --filter <none|scanlines,grid,rounded,scale2x>");
+                    std::process::exit(1);
+                };
+                filters = Some(list);
+            }
+            "--config" => {
+                let Some(path) = args.next() else {
+                    eprintln!("usage: chip8 --config <file>");
+                    std::process::exit(1);
+                };
+                config_path = Some(path);
+            }
+            "--scale" => {
+                let Some(scale) = args.next().and_then(|scale| scale.parse().ok()) else {
+                    eprintln!("usage: chip8 --scale <n>");
+                    std::process::exit(1);
+                };
+                window_scale = scale;
+            }
+            "--scaling" => {
+                let Some(mode) = args.next().and_then(|mode| Scaling::parse(&mode)) else {
+                    eprintln!("usage: chip8 --scaling <integer|fit>");
+                    std::process::exit(1);
+                };
+                scaling = mode;
+            }
+            "--screenshot-scale" => {
+                let Some(scale) = args.next().and_then(|scale| scale.parse().ok()) else {
+                    eprintln!("usage: chip8 --screenshot-scale <n>");
+                    std::process::exit(1);
+                };
+                screenshot_scale = scale;
+            }
+            "--gdb" => {
+                let Some(port) = args.next().and_then(|port| port.parse::<u16>().ok()) else {
+                    eprintln!("usage: chip8 --gdb <port>");
+                    std::process::exit(1);
+                };
+                gdb_port = Some(port);
+            }
+            "--symbols" => {
+                symbols = args.next();
+                debug = true;
+            }
+            "--trace" => {
+                trace_file = args.next();
+                tracing = true;
+            }
+            "--trace-ring" => {
+                trace_ring = args.next().and_then(|n| n.parse().ok());
+                tracing = true;
+            }
+            "--trace-addr" => {
+                let range = args.next().unwrap_or_default();
+                let Some(addresses) = trace::parse_range(&range, 16) else {
+                    eprintln!("bad address range '{range}'");
+                    eprintln!("usage: chip8 --trace-addr <start-end|addr>, in hex");
+                    std::process::exit(1);
+                };
+                trace_filter.addresses = Some(addresses);
+            }
+            "--trace-ops" => {
+                let classes = args.next().unwrap_or_default();
+                let Some(parsed) = trace::parse_classes(&classes) else {
+                    eprintln!("bad opcode classes '{classes}'");
+                    eprintln!(
+                        "usage: chip8 --trace-ops <digit,digit..>, first hex digits of opcodes"
+                    );
+                    std::process::exit(1);
+                };
+                trace_filter.classes = Some(parsed);
+            }
+            "--trace-frames" => {
+                let range = args.next().unwrap_or_default();
+                let Some(frames) = trace::parse_range(&range, 10) else {
+                    eprintln!("bad frame range '{range}'");
+                    eprintln!("usage: chip8 --trace-frames <start-end|frame>");
+                    std::process::exit(1);
+                };
+                trace_filter.frames = Some(frames);
+            }
+            "--profile" => {
+                let Some(prefix) = args.next() else {
+                    eprintln!("usage: chip8 --profile <output prefix>");
+                    std::process::exit(1);
+                };
+                profile = Some(prefix);
+            }
+            _ => rom = Some(arg),
         }
     }
-    fn press(&mut self) {
-        self.pressed_frames_ago = 0;
-    }
-    fn release(&mut self) {
-        self.released_frames_ago = 0;
-    }
-    fn update_pressed(&mut self) {
-        self.pressed_frames_ago = (self.pressed_frames_ago + 1).min(60);
-    }
-    fn update_released(&mut self) {
-        self.released_frames_ago = (self.released_frames_ago + 1).min(60);
-    }
-    fn is_pressed(&self) -> bool {
-        self.pressed_frames_ago == 1
-    }
-}
+    let mut tracer = tracing
+        .then(|| trace::Tracer::new(trace_file.as_deref(), trace_filter, trace_ring).unwrap());
 
-#[derive(Clone, Copy, Debug)]
-enum KeypadKey {
-    Key0 = 0x0,
-    Key1 = 0x1,
-    Key2 = 0x2,
-    Key3 = 0x3,
-    Key4 = 0x4,
-    Key5 = 0x5,
-    Key6 = 0x6,
-    Key7 = 0x7,
-    Key8 = 0x8,
-    Key9 = 0x9,
-    KeyA = 0xA,
-    KeyB = 0xB,
-    KeyC = 0xC,
-    KeyD = 0xD,
-    KeyE = 0xE,
-    KeyF = 0xF,
-}
-
-fn main() {
     let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(ControlFlow::Poll);
 
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
+        let size = LogicalSize::new(
+            (WIDTH * window_scale) as f64,
+            (HEIGHT * window_scale) as f64,
+        );
         WindowBuilder::new()
             .with_title("CHIP8")
             .with_inner_size(size)
-            .with_min_inner_size(size)
+            .with_min_inner_size(LogicalSize::new(WIDTH as f64, HEIGHT as f64))
             .build(&event_loop)
             .unwrap()
     };
 
+    // the game or the overlay is drawn into `frame` and scaled up into the pixels buffer,
+    // which the window shows unscaled in its middle
+    let mut frame_size = (WIDTH, HEIGHT);
+    let mut frame = vec![0; WIDTH * HEIGHT * 4];
+    let mut output_size = (WIDTH, HEIGHT);
     let mut pixels = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
@@ -90,6 +219,9 @@ fn main() {
     };
 
     let mut interpreter = Interpreter::new();
+    interpreter.quirks = quirks;
+    interpreter.quirks.display_wait |= display_wait;
+    interpreter.timing = timing;
 
     // interpreter.load("roms/test_opcode.ch8").unwrap();
     // interpreter.load("roms/bc_test.ch8").unwrap();
@@ -100,12 +232,63 @@ fn main() {
     // interpreter.load("roms/3-corax+.ch8").unwrap();
     // interpreter.load("roms/4-flags.ch8").unwrap();
     // interpreter.load("roms/5-quirks.ch8").unwrap();
-    interpreter.load("roms/6-keypad.ch8").unwrap();
+    // interpreter.load("roms/6-keypad.ch8").unwrap();
+    let rom = rom.unwrap_or_else(|| "roms/6-keypad.ch8".to_string());
+    interpreter.load(&rom).unwrap();
+
+    // a missing chip8.conf is fine, a missing config given on the command line isn't
+    let config = match config_path {
+        Some(path) => Config::load(&path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            std::process::exit(1);
+        }),
+        None if std::path::Path::new(CONFIG).exists() => {
+            Config::load(CONFIG).unwrap_or_else(|err| {
+                eprintln!("{CONFIG}: {err}");
+                std::process::exit(1);
+            })
+        }
+        None => Config::default(),
+    };
+    interpreter.palette = palette
+        .or_else(|| config.palette_for(&rom))
+        .unwrap_or_default();
+    interpreter.persistence = persistence
+        .or_else(|| config.persistence_for(&rom))
+        .unwrap_or_default();
+    interpreter.filters = filters
+        .or_else(|| config.filters_for(&rom))
+        .unwrap_or_default();
+
+    let mut debugger = Debugger::new();
+    if debug {
+        // the assembler writes the symbol map next to the ROM
+        let symbols = symbols.unwrap_or_else(|| symbol_path(&rom));
+        match debugger.load_symbols(&symbols) {
+            Ok(()) => println!("loaded symbols from {symbols}"),
+            Err(err) => eprintln!("no symbols loaded from {symbols}: {err}"),
+        }
+        debugger.attach_console();
+    }
+    let mut profiler = profile.as_ref().map(|_| {
+        let symbols = std::fs::read_to_string(symbol_path(&rom))
+            .ok()
+            .and_then(|text| symbols::SymbolMap::parse(&text).ok())
+            .unwrap_or_default();
+        profile::Profiler::new(symbols)
+    });
+    let mut gdb = gdb_port.map(|port| gdb::GdbStub::bind(port).unwrap());
+    let mut recorder = None;
 
     let mut keys = Vec::new();
+    // keys not yet handed to the interpreter, which only sees them when a frame runs
+    let mut pending_keys = Vec::new();
+    let mut show_overlay = false;
+    let mut scheduler = Scheduler::new(TARGET_FPS, Instant::now());
+    // at unlimited speed, how long frames run for between redraws
+    let unlimited_slice = Duration::from_nanos(1_000_000_000 / TARGET_FPS);
 
     let _ = event_loop.run(move |event, elwt| {
-        let start_time = Instant::now();
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
@@ -114,6 +297,14 @@ fn main() {
                 println!("The close button was pressed; stopping");
                 elwt.exit();
             }
+            Event::LoopExiting => {
+                if let (Some(profiler), Some(prefix)) = (&profiler, &profile) {
+                    write_profile(profiler, prefix);
+                }
+                if let Some(recorder) = recorder.take() {
+                    finish_recording(recorder);
+                }
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
@@ -150,482 +341,217 @@ fn main() {
                     elwt.exit();
                 }
 
-                interpreter.update(&keys[..]);
-
-                // Wait for frame
-                let elapsed_time = Instant::now().duration_since(start_time).as_secs_f32();
-                let elapsed_millis = (elapsed_time * 1000.0) as u64;
-                let wait_millis = if 1000 / TARGET_FPS >= elapsed_millis {
-                    1000 / TARGET_FPS - elapsed_millis
-                } else {
-                    0
-                };
-                thread::sleep(std::time::Duration::from_millis(wait_millis));
-
-                keys = Vec::new();
-
-                // Redraw the application.
-                interpreter.draw(pixels.frame_mut());
-                if let Err(err) = pixels.render() {
-                    eprintln!("pixels.render error: {err}");
-                    elwt.exit();
+                // Debug overlay toggle
+                if keys.iter().any(|(key, state)| {
+                    *key == Key::Named(NamedKey::F1) && *state == ElementState::Pressed
+                }) {
+                    show_overlay = !show_overlay;
+                    let (width, height) = if show_overlay {
+                        (overlay::FRAME_WIDTH, overlay::FRAME_HEIGHT)
+                    } else {
+                        (WIDTH, HEIGHT)
+                    };
+                    frame_size = (width, height);
+                    frame = vec![0; width * height * 4];
+                    if show_overlay {
+                        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+                        let _ = window.request_inner_size(LogicalSize::new(
+                            size.width.max(width as f64),
+                            size.height.max(height as f64),
+                        ));
+                    }
                 }
-            }
-            _ => (),
-        }
-    });
-}
-
-fn get_key(key: &str) -> Option<KeypadKey> {
-    // ╔═══╦═══╦═══╦═══╗       ╔═══╦═══╦═══╦═══╗
-    // ║ 1 ║ 2 ║ 3 ║ 4 ║       ║ 1 ║ 2 ║ 3 ║ C ║
-    // ╠═══╬═══╬═══╬═══╣       ╠═══╬═══╬═══╬═══╣
-    // ║ Q ║ W ║ E ║ R ║       ║ 4 ║ 5 ║ 6 ║ D ║
-    // ╠═══╬═══╬═══╬═══╣  -->  ╠═══╬═══╬═══╬═══╣
-    // ║ A ║ S ║ D ║ F ║       ║ 7 ║ 8 ║ 9 ║ E ║
-    // ╠═══╬═══╬═══╬═══╣       ╠═══╬═══╬═══╬═══╣
-    // ║ Z ║ X ║ C ║ V ║       ║ A ║ 0 ║ B ║ F ║
-    // ╚═══╩═══╩═══╩═══╝       ╚═══╩═══╩═══╩═══╝
-    match key {
-        "1" => Some(KeypadKey::Key1),
-        "2" => Some(KeypadKey::Key2),
-        "3" => Some(KeypadKey::Key3),
-        "4" => Some(KeypadKey::KeyC),
-
-        "q" /*| "Q"*/ => Some(KeypadKey::Key4),
-        "w" /*| "W"*/ => Some(KeypadKey::Key5),
-        "e" /*| "E"*/ => Some(KeypadKey::Key6),
-        "r" /*| "R"*/ => Some(KeypadKey::KeyD),
 
-        "a" /*| "A"*/ => Some(KeypadKey::Key7),
-        "s" /*| "S"*/ => Some(KeypadKey::Key8),
-        "d" /*| "D"*/ => Some(KeypadKey::Key9),
-        "f" /*| "F"*/ => Some(KeypadKey::KeyE),
-
-        "z" /*| "Z"*/ => Some(KeypadKey::KeyA),
-        "x" /*| "X"*/ => Some(KeypadKey::Key0),
-        "c" /*| "C"*/ => Some(KeypadKey::KeyB),
-        "v" /*| "V"*/ => Some(KeypadKey::KeyF),
-
-        _ => None,
-    }
-}
-
-enum KeyStatus {
-    NoKeyAwait,
-    KeyAwait,
-    KeyConf(KeypadKey),
-}
-
-struct Interpreter {
-    memory: Vec<u8>,
-    screen: [[bool; WIDTH]; HEIGHT],
-    program_counter: usize,
-    index: u16,
-    stack: Vec<u16>,
-    delay_timer: u8,
-    sound_timer: u8,
-    registers: [u8; 16],
-    halt: bool,
-    keys: [KeyState; 16],
-    key_wait_status: KeyStatus,
-}
-
-impl Interpreter {
-    fn new() -> Self {
-        Self {
-            memory: vec![0; 4096],
-            screen: [[false; WIDTH]; HEIGHT],
-            program_counter: OFFSET,
-            index: 0,
-            stack: vec![],
-            delay_timer: 0,
-            sound_timer: 0,
-            registers: [0; 16],
-            halt: false,
-            keys: [KeyState::new(); 16],
-            key_wait_status: KeyStatus::NoKeyAwait,
-        }
-    }
-
-    fn load(&mut self, filename: &str) -> Result<(), std::io::Error> {
-        let bytes = std::fs::read(filename)?;
-
-        // load font
-        self.memory[0..80].copy_from_slice(&[
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ]);
-
-        // load program
-        self.memory[OFFSET..OFFSET + bytes.len()].copy_from_slice(&bytes);
-        Ok(())
-    }
-
-    fn read_opcode(&self) -> u16 {
-        let p = self.program_counter;
-        let op_byte1 = self.memory[p] as u16;
-        let op_byte2 = self.memory[p + 1] as u16;
-
-        op_byte1 << 8 | op_byte2
-    }
-
-    fn exe(&mut self) {
-        if self.halt {
-            return;
-        }
-
-        let opcode = self.read_opcode();
-        self.program_counter += 2;
-
-        // println!("{:04x}", opcode);
-        // println!("{:?}", self.stack);
-
-        let c = ((opcode & 0xF000) >> 12) as u8;
-        let x = ((opcode & 0x0F00) >> 8) as u8;
-        let y = ((opcode & 0x00F0) >> 4) as u8;
-        let n = (opcode & 0x000F) as u8;
-
-        let nn = opcode & 0x00FF;
-        let nnn = opcode & 0x0FFF;
-
-        match (c, x, y, n) {
-            (0x0, 0x0, 0xE, 0x0) => {
-                //Clear the screen
-                for row in &mut self.screen {
-                    for pix in row {
-                        *pix = false;
+                // Pause, frame advance, speed and memory editing
+                let mut advance = false;
+                for (key, state) in &keys {
+                    if *state != ElementState::Pressed {
+                        continue;
+                    }
+                    if *key == Key::Named(NamedKey::F2) {
+                        debugger.toggle_pause(&interpreter);
+                    } else if *key == Key::Named(NamedKey::F3) {
+                        advance = debugger.is_paused();
+                    } else if *key == Key::Named(NamedKey::F4) {
+                        scheduler.set_speed(Speed::Normal);
+                    } else if *key == Key::Named(NamedKey::F5) {
+                        scheduler.set_speed(scheduler.speed().slower());
+                    } else if *key == Key::Named(NamedKey::F6) {
+                        scheduler.set_speed(scheduler.speed().faster());
+                    } else if *key == Key::Named(NamedKey::F11) {
+                        let fullscreen = match window.fullscreen() {
+                            Some(_) => None,
+                            None => Some(Fullscreen::Borderless(None)),
+                        };
+                        window.set_fullscreen(fullscreen);
+                    } else if *key == Key::Named(NamedKey::F9) {
+                        recorder = toggle_recording(recorder.take(), &rom, screenshot_scale);
+                    } else if *key == Key::Named(NamedKey::F12) {
+                        let dir = std::path::Path::new(".");
+                        match headless::screenshot(&interpreter, &rom, dir, screenshot_scale) {
+                            Ok(path) => println!("saved {}", path.display()),
+                            Err(err) => eprintln!("could not save screenshot: {err}"),
+                        }
+                    } else if show_overlay {
+                        let editable = debugger.is_paused();
+                        debugger
+                            .memory
+                            .handle_key(key, &mut interpreter.memory, editable);
                     }
                 }
-            }
-            (0x0, 0x0, 0xE, 0xE) => {
-                //Return from a subroutine
-                let addr = self.stack.pop().unwrap();
-                self.program_counter = addr as usize;
-            }
-            (0x0, ..) => {
-                //TODO Execute machine language subroutine at address NNN
-                panic!()
-            }
-            (0x1, ..) => {
-                //Jump to address NNN
-                self.program_counter = nnn as usize;
-            }
-            (0x2, ..) => {
-                // Execute subroutine starting at address NNN
-                self.stack.push(self.program_counter as u16);
-                self.program_counter = nnn as usize;
-            }
-            (0x3, ..) => {
-                //Skip the following instruction if the value of register VX equals NN
-                if self.registers[x as usize] == nn as u8 {
-                    self.program_counter += 2;
-                }
-            }
-            (0x4, ..) => {
-                //Skip the following instruction if the value of register VX is not equal to NN
-                if self.registers[x as usize] != nn as u8 {
-                    self.program_counter += 2;
-                }
-            }
-            (0x5, ..) => {
-                //Skip the following instruction if the value of register VX is equal to the value of register VY
-                if self.registers[x as usize] == self.registers[y as usize] {
-                    self.program_counter += 2;
-                }
-            }
-            (0x6, ..) => {
-                //Store number NN in register VX
-                self.registers[x as usize] = nn as u8;
-            }
-            (0x7, ..) => {
-                //Add the value NN to register VX
-                self.registers[x as usize] += nn as u8;
-            }
-            (0x8, _, _, 0x0) => {
-                //Store the value of register VY in register VX
-                self.registers[x as usize] = self.registers[y as usize];
-            }
-            (0x8, _, _, 0x1) => {
-                //Set VX to VX OR VY
-                self.registers[x as usize] |= self.registers[y as usize];
-                if VF_RESET {
-                    self.registers[0xF] = 0x00;
-                }
-            }
-            (0x8, _, _, 0x2) => {
-                //Set VX to VX AND VY
-                self.registers[x as usize] &= self.registers[y as usize];
-                if VF_RESET {
-                    self.registers[0xF] = 0x00;
-                }
-            }
-            (0x8, _, _, 0x3) => {
-                //Set VX to VX XOR VY
-                self.registers[x as usize] ^= self.registers[y as usize];
-                if VF_RESET {
-                    self.registers[0xF] = 0x00;
-                }
-            }
-            (0x8, _, _, 0x4) => {
-                // Add the value of register VY to register VX
-                // Set VF to 01 if a carry occurs
-                // Set VF to 00 if a carry does not occur
-                let (val, carry) =
-                    self.registers[x as usize].overflowing_add(self.registers[y as usize]);
 
-                self.registers[x as usize] = val;
-                self.registers[0xF] = if carry { 0x01 } else { 0x00 };
-            }
-            (0x8, _, _, 0x5) => {
-                // Subtract the value of register VY from register VX
-                // Set VF to 00 if a borrow occurs
-                // Set VF to 01 if a borrow does not occur
-                let (val, borrow) =
-                    self.registers[x as usize].overflowing_sub(self.registers[y as usize]);
-
-                self.registers[x as usize] = val;
-                self.registers[0xF] = if borrow { 0x00 } else { 0x01 };
-            }
-            (0x8, _, _, 0x6) => {
-                // Store the value of register VY shifted right one bit in register VX¹
-                // Set register VF to the least significant bit prior to the shift
-                // VY is unchanged
-                if MODERN_SHIFT_BEHAVIOUR {
-                    let bit = self.registers[x as usize] & 0b0000_0001;
-                    self.registers[x as usize] >>= 1;
-                    self.registers[0xF] = bit;
-                } else {
-                    let bit = self.registers[y as usize] & 0b0000_0001;
-                    self.registers[x as usize] = self.registers[y as usize] >> 1;
-                    self.registers[0xF] = bit;
-                }
-            }
-            (0x8, _, _, 0x7) => {
-                // Set register VX to the value of VY minus VX
-                // Set VF to 00 if a borrow occurs
-                // Set VF to 01 if a borrow does not occur
-                let (val, borrow) =
-                    self.registers[y as usize].overflowing_sub(self.registers[x as usize]);
+                pending_keys.append(&mut keys);
 
-                self.registers[x as usize] = val;
-                self.registers[0xF] = if borrow { 0x00 } else { 0x01 };
-            }
-            (0x8, _, _, 0xE) => {
-                // Store the value of register VY shifted left one bit in register VX¹
-                // Set register VF to the most significant bit prior to the shift
-                // VY is unchanged
-                if MODERN_SHIFT_BEHAVIOUR {
-                    let bit = (self.registers[x as usize] & 0b1000_0000) >> 7;
-                    self.registers[x as usize] <<= 1;
-                    self.registers[0xF] = bit;
-                } else {
-                    let bit = (self.registers[y as usize] & 0b1000_0000) >> 7;
-                    self.registers[x as usize] = self.registers[y as usize] << 1;
-                    self.registers[0xF] = bit;
+                if let Some(gdb) = &mut gdb {
+                    gdb.poll(&mut interpreter, &mut debugger);
                 }
-            }
-            (0x9, ..) => {
-                //Skip the following instruction if the value of register VX is not equal to the value of register VY
-                if self.registers[x as usize] != self.registers[y as usize] {
-                    self.program_counter += 2;
+                debugger.poll(&mut interpreter);
+                // one frame while paused, without stopping at a breakpoint on the PC
+                if advance {
+                    debugger.resume(&interpreter);
                 }
-            }
-            (0xA, ..) => {
-                self.index = nnn;
-            }
-            (0xB, ..) => {
-                //Jump to address NNN + V0
-                self.program_counter = nnn as usize + self.registers[0] as usize;
-            }
-            (0xC, ..) => {
-                //Set VX to a random number with a mask of NN
-                self.registers[x as usize] = rand::random::<u8>() & nn as u8;
-            }
-            (0xD, ..) => {
-                // Draw a sprite at position VX, VY with N bytes of sprite data starting at the address stored in I
-                // Set VF to 01 if any set pixels are changed to unset, and 00 otherwise
-                self.registers[0xF] = 0x00;
-
-                let x = self.registers[x as usize] as usize % WIDTH;
-                let y = self.registers[y as usize] as usize % HEIGHT;
-
-                let clipped_n = (y + n as usize).min(HEIGHT) - y;
-
-                for i in 0..clipped_n {
-                    let byte = self.memory[self.index as usize + i];
-
-                    let bits: &mut [bool] = &mut self.screen[y + i][x..(x + 8).min(WIDTH)];
-                    for (i, bit) in bits.iter_mut().enumerate() {
-                        let new = (byte >> (7 - i)) % 2 == 1;
-                        if *bit && new {
-                            self.registers[0xF] = 0x01;
-                        }
-                        *bit ^= new;
+                let started = Instant::now();
+                let due = scheduler.frames_due(started);
+                let frames = if advance { 1 } else { due };
+                for _ in 0..frames {
+                    if debugger.is_paused()
+                        || (scheduler.speed() == Speed::Unlimited
+                            && started.elapsed() >= unlimited_slice)
+                    {
+                        break;
                     }
-                }
-            }
-            (0xE, _, 0x9, 0xE) => {
-                //Skip the following instruction if the key corresponding to the hex value currently stored in register VX is pressed
-                if self.is_key_pressed(self.registers[x as usize]) {
-                    self.program_counter += 2;
-                }
-            }
-            (0xE, _, 0xA, 0x1) => {
-                //Skip the following instruction if the key corresponding to the hex value currently stored in register VX is not pressed
-                if !self.is_key_pressed(self.registers[x as usize]) {
-                    self.program_counter += 2;
-                }
-            }
-            (0xF, _, 0x0, 0x7) => {
-                //Store the current value of the delay timer in register VX
-                self.registers[x as usize] = self.delay_timer;
-            }
-            (0xF, _, 0x0, 0xA) => {
-                // Wait for a keypress and store the result in register VX
-                // On the original COSMAC VIP, the key was only registered when it was pressed and then released.
-                use KeyStatus as KS;
-                self.key_wait_status = match self.key_wait_status {
-                    KS::KeyConf(key) => {
-                        self.registers[x as usize] = key as u8;
-                        KS::NoKeyAwait
+                    let mut observers: Vec<&mut dyn Observer> = vec![&mut debugger];
+                    if let Some(tracer) = &mut tracer {
+                        observers.push(tracer);
+                    }
+                    if let Some(profiler) = &mut profiler {
+                        observers.push(profiler);
+                    }
+                    if let Some(recorder) = &mut recorder {
+                        observers.push(recorder);
                     }
-                    _ => {
-                        self.program_counter -= 2;
-                        KS::KeyAwait
+                    if let Err(err) = interpreter.update(&pending_keys, &mut observers) {
+                        eprintln!("{err}");
+                        debugger.pause();
                     }
+                    pending_keys.clear();
                 }
-            }
-            (0xF, _, 0x1, 0x5) => {
-                //Set the delay timer to the value of register VX
-                self.delay_timer = self.registers[x as usize];
-            }
-            (0xF, _, 0x1, 0x8) => {
-                //Set the sound timer to the value of register VX
-                self.sound_timer = self.registers[x as usize];
-            }
-            (0xF, _, 0x1, 0xE) => {
-                //Add the value stored in register VX to register I
-                if AMIGA_BEHAVIOUR {
-                    // set VF to 1 if index overflow
-                    let prev = self.index <= 0xFFF;
-
-                    self.index += self.registers[x as usize] as u16;
+                if advance && !debugger.is_paused() {
+                    debugger.pause();
+                }
+                if debugger.is_paused() {
+                    pending_keys.clear();
+                }
+                debugger.memory.end_frame(&interpreter.memory);
+                elwt.set_control_flow(ControlFlow::WaitUntil(scheduler.next_frame()));
 
-                    if prev && self.index > 0x0FFF {
-                        self.registers[0xF] = 0x1;
-                    } else {
-                        self.registers[0xF] = 0x0;
-                    }
+                // Redraw the application.
+                if show_overlay {
+                    overlay::draw(&interpreter, &debugger, &mut frame);
                 } else {
-                    self.index += self.registers[x as usize] as u16;
+                    interpreter.draw(&mut frame);
                 }
-            }
-            (0xF, _, 0x2, 0x9) => {
-                //Set I to the memory address of the sprite data corresponding to the hexadecimal digit stored in register VX
-                self.index = self.registers[x as usize] as u16 * 5; // hardcoded in the load method
-            }
-            (0xF, _, 0x3, 0x3) => {
-                //Store the binary-coded decimal equivalent of the value stored in register VX at addresses I, I + 1, and I + 2
-                self.memory[self.index as usize] = self.registers[x as usize] / 100;
-                self.memory[self.index as usize + 1] = (self.registers[x as usize] / 10) % 10;
-                self.memory[self.index as usize + 2] = self.registers[x as usize] % 10;
-            }
-            (0xF, _, 0x5, 0x5) => {
-                //Store the values of registers V0 to VX inclusive in memory starting at address I
-                //I is set to I + X + 1 after operation²
-                if MODERN_STR_LD_BEHAVIOUR {
-                    for i in 0..=x as usize {
-                        self.memory[self.index as usize + i] = self.registers[i];
-                    }
-                } else {
-                    for i in 0..=x as usize {
-                        self.memory[self.index as usize] = self.registers[i];
-                        self.index += 1;
-                    }
+                if debugger.is_paused() {
+                    overlay::draw_badge(&mut frame, show_overlay, "PAUSED");
+                } else if scheduler.speed() != Speed::Normal {
+                    overlay::draw_badge(&mut frame, show_overlay, scheduler.speed().label());
                 }
-            }
-            (0xF, _, 0x6, 0x5) => {
-                //Fill registers V0 to VX inclusive with the values stored in memory starting at address I
-                //I is set to I + X + 1 after operation²
-                if MODERN_STR_LD_BEHAVIOUR {
-                    for i in 0..=x as usize {
-                        self.registers[i] = self.memory[self.index as usize + i];
+
+                // Scale it to the window, which may have been resized since the last frame
+                let window_size = window.inner_size();
+                let window_size = (window_size.width as usize, window_size.height as usize);
+                let size = viewport::scaled_size(frame_size, window_size, scaling);
+                if size != output_size {
+                    if let Err(err) = pixels.resize_buffer(size.0 as u32, size.1 as u32) {
+                        eprintln!("pixels.resize_buffer error: {err}");
+                        elwt.exit();
                     }
+                    output_size = size;
+                }
+                // the overlay's text stays sharp
+                let filters: &[Filter] = if show_overlay {
+                    &[]
                 } else {
-                    for i in 0..=x as usize {
-                        self.registers[i] = self.memory[self.index as usize];
-                        self.index += 1;
-                    }
+                    &interpreter.filters
+                };
+                filter::render(&frame, frame_size, pixels.frame_mut(), output_size, filters);
+                if let Err(err) = pixels.render() {
+                    eprintln!("pixels.render error: {err}");
+                    elwt.exit();
                 }
             }
-            _ => {
-                panic!("wrong opcode {:04x}", opcode);
-            }
+            _ => (),
         }
-    }
-
-    fn draw(&self, frame: &mut [u8]) {
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let rgba = if self.screen[i / WIDTH][i % WIDTH] {
-                [0x0, 0x0, 0x0, 0xff]
-            } else {
-                [0xff, 0xff, 0xff, 0xff]
-            };
+    });
+}
 
-            pixel.copy_from_slice(&rgba);
+fn assemble(source: &str, output: &str) {
+    let text = std::fs::read_to_string(source).unwrap();
+    match asm::assemble(&text, source) {
+        Ok((bytes, symbols)) => {
+            std::fs::write(output, bytes).unwrap();
+            std::fs::write(symbol_path(output), symbols.to_string()).unwrap();
+        }
+        Err(err) => {
+            eprintln!("{source}: {err}");
+            std::process::exit(1);
         }
     }
+}
 
-    fn update(&mut self, keys: &[(Key, ElementState)]) {
-        for (key, state) in keys {
-            if let Some(key) = key.to_text().and_then(get_key) {
-                match state {
-                    ElementState::Pressed => {
-                        self.keys[key as usize].press();
-                    }
-                    ElementState::Released => {
-                        if let KeyStatus::KeyAwait = self.key_wait_status {
-                            self.key_wait_status = KeyStatus::KeyConf(key);
-                        }
-
-                        self.keys[key as usize].release();
-                    }
-                }
-                println!("{key:?} {state:?}");
-            }
-        }
+fn write_profile(profiler: &profile::Profiler, prefix: &str) {
+    let report = format!("{prefix}.txt");
+    let folded = format!("{prefix}.folded");
+    match std::fs::write(&report, profiler.report())
+        .and_then(|()| std::fs::write(&folded, profiler.folded()))
+    {
+        Ok(()) => println!("wrote profile to {report} and {folded}"),
+        Err(err) => eprintln!("could not write profile: {err}"),
+    }
+}
 
-        for _ in 0..IPF {
-            self.exe();
+// finish the recording if there is one, or start recording to a GIF and a WAV named
+// after the ROM and the time
+fn toggle_recording(
+    recorder: Option<record::Recorder>,
+    rom: &str,
+    scale: usize,
+) -> Option<record::Recorder> {
+    if let Some(recorder) = recorder {
+        finish_recording(recorder);
+        return None;
+    }
+    let now = std::time::SystemTime::now();
+    let dir = std::path::Path::new(".");
+    let outputs = record::Outputs {
+        gif: Some(screen::capture_path(dir, rom, now, "gif")),
+        frames: None,
+        wav: Some(screen::capture_path(dir, rom, now, "wav")),
+        scale,
+    };
+    match record::Recorder::start(&outputs) {
+        Ok(recorder) => {
+            println!("recording to {}", outputs.gif.unwrap().display());
+            Some(recorder)
         }
-
-        // update timers
-        self.delay_timer = self.delay_timer.saturating_sub(1);
-        self.sound_timer = self.sound_timer.saturating_sub(1);
-
-        // update kets
-        for key in &mut self.keys {
-            key.update_pressed();
-            key.update_released();
+        Err(err) => {
+            eprintln!("could not start recording: {err}");
+            None
         }
     }
+}
 
-    fn is_key_pressed(&self, key: u8) -> bool {
-        self.keys[key as usize].is_pressed()
+fn finish_recording(recorder: record::Recorder) {
+    let frames = recorder.frames();
+    match recorder.finish() {
+        Ok(()) => println!("recorded {frames} frames"),
+        Err(err) => eprintln!("could not finish recording: {err}"),
     }
 }
+
+fn symbol_path(rom: &str) -> String {
+    std::path::Path::new(rom)
+        .with_extension("sym")
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,194 @@
+use crate::debugger::Debugger;
+use crate::disasm::disassemble;
+use crate::font::{draw_text, CHAR_HEIGHT, CHAR_WIDTH};
+use crate::hexview::{ROWS, ROW_BYTES};
+use crate::{Interpreter, HEIGHT, WIDTH};
+
+/// Size of a CHIP-8 pixel while the overlay is shown.
+const SCALE: usize = 4;
+const PANEL_COLUMNS: usize = 40;
+
+pub const FRAME_WIDTH: usize = WIDTH * SCALE + PANEL_COLUMNS * CHAR_WIDTH;
+pub const FRAME_HEIGHT: usize = HEIGHT * SCALE + 2 + ROWS * CHAR_HEIGHT;
+
+const BACKGROUND: [u8; 4] = [0x20, 0x20, 0x20, 0xff];
+const TEXT: [u8; 4] = [0xe0, 0xe0, 0xe0, 0xff];
+const HIGHLIGHT: [u8; 4] = [0xff, 0xd0, 0x40, 0xff];
+const DIM: [u8; 4] = [0x70, 0x70, 0x70, 0xff];
+const INDEX: [u8; 4] = [0x40, 0xd0, 0xff, 0xff];
+const CHANGED: [u8; 4] = [0xff, 0x50, 0x50, 0xff];
+const CURSOR: [u8; 4] = [0x50, 0xff, 0x50, 0xff];
+
+// keypad keys in the order they are laid out on the COSMAC VIP
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Draw the game scaled up, with a panel showing the machine state on its right
+/// and a hex view of memory below, into a frame of `FRAME_WIDTH` x `FRAME_HEIGHT` pixels.
+pub fn draw(interpreter: &Interpreter, debugger: &Debugger, frame: &mut [u8]) {
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % FRAME_WIDTH, i / FRAME_WIDTH);
+        let rgba = if x >= WIDTH * SCALE || y >= HEIGHT * SCALE {
+            BACKGROUND
+        } else {
+            interpreter.pixel_rgba(x / SCALE, y / SCALE)
+        };
+        pixel.copy_from_slice(&rgba);
+    }
+
+    let mut panel = Panel { frame, line: 0 };
+
+    for (i, values) in interpreter.registers.chunks(4).enumerate() {
+        let text: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(j, v)| format!("V{:X} {v:02X}", i * 4 + j))
+            .collect();
+        panel.print(0, &text.join("  "), TEXT);
+    }
+    panel.print(
+        0,
+        &format!(
+            "I {:03X}  PC {:03X}  SP {}",
+            interpreter.index,
+            interpreter.program_counter,
+            interpreter.stack.len()
+        ),
+        TEXT,
+    );
+    panel.print(
+        0,
+        &format!(
+            "DT {:02X}  ST {:02X}",
+            interpreter.delay_timer, interpreter.sound_timer
+        ),
+        TEXT,
+    );
+
+    let stack: Vec<String> = interpreter
+        .stack
+        .iter()
+        .map(|a| format!("{a:03X}"))
+        .collect();
+    for (i, chunk) in stack.chunks(8).enumerate() {
+        let label = if i == 0 { "STACK" } else { "" };
+        panel.print(0, &format!("{label:<6}{}", chunk.join(" ")), TEXT);
+    }
+    if stack.is_empty() {
+        panel.print(0, "STACK", TEXT);
+    }
+
+    for (i, row) in KEYPAD.iter().enumerate() {
+        if i == 0 {
+            panel.text(0, "KEYS", TEXT);
+        }
+        for (j, &key) in row.iter().enumerate() {
+            let rgba = if interpreter.keys[key as usize].is_down() {
+                HIGHLIGHT
+            } else {
+                DIM
+            };
+            panel.text(6 + j * 2, &format!("{key:X}"), rgba);
+        }
+        panel.line += 1;
+    }
+
+    panel.line += 1;
+    for i in 0..5 {
+        let addr = interpreter.program_counter + i * 2;
+        let Some(bytes) = interpreter.memory.get(addr..addr + 2) else {
+            break;
+        };
+        let opcode = (bytes[0] as u16) << 8 | bytes[1] as u16;
+        let text = disassemble(opcode).unwrap_or_else(|| "???".to_string());
+        let (marker, rgba) = if i == 0 {
+            (">", HIGHLIGHT)
+        } else {
+            (" ", TEXT)
+        };
+        panel.print(0, &format!("{marker}{addr:03X} {opcode:04X} {text}"), rgba);
+    }
+    if debugger.is_paused() {
+        panel.line += 1;
+        panel.print(0, "PAUSED", HIGHLIGHT);
+    }
+
+    draw_memory(interpreter, debugger, panel.frame);
+}
+
+/// Draw `text` on a dark box in the top right corner of the game, in a frame drawn by `draw`
+/// when `overlay` is set and by `Interpreter::draw` otherwise.
+pub fn draw_badge(frame: &mut [u8], overlay: bool, text: &str) {
+    let (width, right) = match overlay {
+        true => (FRAME_WIDTH, WIDTH * SCALE),
+        false => (WIDTH, WIDTH),
+    };
+    let left = right.saturating_sub(text.len() * CHAR_WIDTH + 1);
+    for y in 0..CHAR_HEIGHT + 1 {
+        for x in left..right {
+            let offset = (y * width + x) * 4;
+            frame[offset..offset + 4].copy_from_slice(&BACKGROUND);
+        }
+    }
+    draw_text(frame, width, left + 1, 1, text, HIGHLIGHT);
+}
+
+// rows of hex below the game, the PC, I, changed bytes and the edit cursor in color
+fn draw_memory(interpreter: &Interpreter, debugger: &Debugger, frame: &mut [u8]) {
+    let view = &debugger.memory;
+    let pc = interpreter.program_counter;
+    let index = interpreter.index as usize;
+
+    for row in 0..ROWS {
+        let start = view.top + row * ROW_BYTES;
+        let y = HEIGHT * SCALE + 2 + row * CHAR_HEIGHT;
+        draw_text(frame, FRAME_WIDTH, 2, y, &format!("{start:03X}"), DIM);
+
+        for (i, addr) in (start..start + ROW_BYTES).enumerate() {
+            let Some(byte) = interpreter.memory.get(addr) else {
+                break;
+            };
+            let mut text = format!("{byte:02X}");
+            let rgba = if addr == view.cursor {
+                if let Some(nibble) = view.pending_nibble() {
+                    text = format!("{nibble:X}_");
+                }
+                CURSOR
+            } else if addr == pc || addr == pc + 1 {
+                HIGHLIGHT
+            } else if addr == index {
+                INDEX
+            } else if view.is_changed(addr) {
+                CHANGED
+            } else {
+                TEXT
+            };
+            let x = 2 + (4 + i * 3) * CHAR_WIDTH;
+            draw_text(frame, FRAME_WIDTH, x, y, &text, rgba);
+        }
+    }
+}
+
+// lines of text drawn top to bottom in the panel
+struct Panel<'a> {
+    frame: &'a mut [u8],
+    line: usize,
+}
+
+impl Panel<'_> {
+    // draw on the current line without moving to the next one
+    fn text(&mut self, column: usize, text: &str, rgba: [u8; 4]) {
+        let x = WIDTH * SCALE + 2 + column * CHAR_WIDTH;
+        let y = 1 + self.line * CHAR_HEIGHT;
+        draw_text(self.frame, FRAME_WIDTH, x, y, text, rgba);
+    }
+
+    fn print(&mut self, column: usize, text: &str, rgba: [u8; 4]) {
+        self.text(column, text, rgba);
+        self.line += 1;
+    }
+}
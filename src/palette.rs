@@ -0,0 +1,114 @@
+/// Colors the screen is drawn in, indexed by the bit planes a pixel is lit in: the background,
+/// the first plane, the second plane and both, as in XO-CHIP. With the single plane of
+/// CHIP-8 only the first two are used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 4],
+}
+
+impl Palette {
+    /// Black on white.
+    pub const MONO: Palette = Palette {
+        colors: [
+            [0xff, 0xff, 0xff],
+            [0x00, 0x00, 0x00],
+            [0x80, 0x80, 0x80],
+            [0x40, 0x40, 0x40],
+        ],
+    };
+
+    /// A green phosphor monitor.
+    pub const GREEN: Palette = Palette {
+        colors: [
+            [0x0a, 0x14, 0x0a],
+            [0x33, 0xff, 0x66],
+            [0x1a, 0x99, 0x3d],
+            [0xb3, 0xff, 0xcc],
+        ],
+    };
+
+    /// An amber phosphor monitor.
+    pub const AMBER: Palette = Palette {
+        colors: [
+            [0x14, 0x0c, 0x00],
+            [0xff, 0xb0, 0x00],
+            [0x99, 0x66, 0x00],
+            [0xff, 0xe0, 0x99],
+        ],
+    };
+
+    /// Octo's default colors.
+    pub const OCTO: Palette = Palette {
+        colors: [
+            [0x99, 0x66, 0x00],
+            [0xff, 0xcc, 0x00],
+            [0xff, 0x66, 0x00],
+            [0x66, 0x22, 0x00],
+        ],
+    };
+
+    /// A greenish monochrome LCD.
+    pub const LCD: Palette = Palette {
+        colors: [
+            [0xc4, 0xcf, 0xa1],
+            [0x41, 0x49, 0x2f],
+            [0x8b, 0x95, 0x6d],
+            [0x1f, 0x1f, 0x1f],
+        ],
+    };
+
+    pub const THEMES: [(&'static str, Palette); 5] = [
+        ("mono", Palette::MONO),
+        ("green", Palette::GREEN),
+        ("amber", Palette::AMBER),
+        ("octo", Palette::OCTO),
+        ("lcd", Palette::LCD),
+    ];
+
+    /// The theme called `name`, as listed in `THEMES`.
+    pub fn theme(name: &str) -> Option<Palette> {
+        Self::THEMES
+            .iter()
+            .find(|(theme, _)| *theme == name)
+            .map(|&(_, palette)| palette)
+    }
+
+    /// Parse a theme name, or two to four comma separated `rrggbb` colors starting with the
+    /// background. Colors left out are those of the foreground.
+    pub fn parse(text: &str) -> Option<Palette> {
+        if let Some(palette) = Self::theme(text) {
+            return Some(palette);
+        }
+        let colors = text
+            .split(',')
+            .map(|color| parse_color(color.trim()))
+            .collect::<Option<Vec<_>>>()?;
+        if !(2..=4).contains(&colors.len()) {
+            return None;
+        }
+        let mut palette = [colors[1]; 4];
+        palette[..colors.len()].copy_from_slice(&colors);
+        Some(Palette { colors: palette })
+    }
+
+    /// The RGBA color of a pixel lit in the planes set in `planes`.
+    pub fn rgba(&self, planes: usize) -> [u8; 4] {
+        let [r, g, b] = self.colors[planes & 3];
+        [r, g, b, 0xff]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::MONO
+    }
+}
+
+fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let text = text.trim_start_matches('#');
+    if text.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(text, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
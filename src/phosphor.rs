@@ -0,0 +1,42 @@
+/// How pixels fade in and out, to hide the flicker of sprites being erased and drawn again.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Persistence {
+    /// Pixels are lit or not.
+    #[default]
+    Off,
+    /// Lit pixels show at full brightness, and keep `strength` of their brightness every
+    /// frame after they go out.
+    Decay(f32),
+    /// Every frame is blended with the ones before it, `strength` being the weight of the
+    /// ones before.
+    Blend(f32),
+}
+
+impl Persistence {
+    /// Parse `off`, `decay:<strength>` or `blend:<strength>`, the strength from 0 to 1.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (mode, strength) = match text.split_once(':') {
+            Some((mode, strength)) => (mode, strength.parse::<f32>().ok()?),
+            None if text == "off" => return Some(Persistence::Off),
+            None => return None,
+        };
+        if !(0.0..=1.0).contains(&strength) {
+            return None;
+        }
+        match mode {
+            "decay" => Some(Persistence::Decay(strength)),
+            "blend" => Some(Persistence::Blend(strength)),
+            _ => None,
+        }
+    }
+
+    /// The brightness of a pixel after a frame, from its brightness before and whether it's lit.
+    pub fn fade(self, brightness: f32, lit: bool) -> f32 {
+        let lit = lit as u8 as f32;
+        match self {
+            Persistence::Off => lit,
+            Persistence::Decay(strength) => lit.max(brightness * strength),
+            Persistence::Blend(strength) => brightness * strength + lit * (1.0 - strength),
+        }
+    }
+}
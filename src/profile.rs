@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::disasm::disassemble;
+use crate::symbols::SymbolMap;
+use crate::{ExeError, Interpreter, Observer, Timing, OFFSET};
+
+const HOT_ADDRESSES: usize = 30;
+
+#[derive(Default)]
+struct Subroutine {
+    calls: u64,
+    inclusive: u64,
+    exclusive: u64,
+}
+
+/// Counts the cycles of executed instructions per address and per subroutine, and the
+/// instructions per frame. With `Timing::Vip` a cycle is a machine cycle of the VIP, with the
+/// other timings every instruction takes one.
+pub struct Profiler {
+    symbols: SymbolMap,
+    // cycles, and the instructions they were spent on
+    total: u64,
+    instructions: u64,
+    addresses: Vec<u64>,
+    executions: Vec<u64>,
+    opcodes: Vec<u16>,
+    subroutines: HashMap<usize, Subroutine>,
+    // subroutines entered through `2NNN`, with the cycle count on entry
+    calls: Vec<(usize, u64)>,
+    // cycles per call stack, for flame graphs
+    stacks: HashMap<Vec<usize>, u64>,
+    frames: Vec<u64>,
+    frame_instructions: u64,
+    // instructions per frame, when the interpreter runs a fixed number of them
+    ipf: Option<usize>,
+    // cycles of instructions that left the PC where it was, like `FX0A` waiting for a key
+    idle: u64,
+    // cycles of the instruction about to run, worked out before it changes its operands
+    cost: u64,
+}
+
+impl Profiler {
+    pub fn new(symbols: SymbolMap) -> Self {
+        Self {
+            symbols,
+            total: 0,
+            instructions: 0,
+            addresses: vec![0; 4096],
+            executions: vec![0; 4096],
+            opcodes: vec![0; 4096],
+            subroutines: HashMap::new(),
+            calls: vec![],
+            stacks: HashMap::new(),
+            frames: vec![],
+            frame_instructions: 0,
+            ipf: None,
+            idle: 0,
+            cost: 0,
+        }
+    }
+
+    fn name(&self, addr: usize) -> String {
+        match self.symbols.describe(addr as u16) {
+            Some(label) if !label.contains('+') => label,
+            _ if addr == OFFSET => "main".to_string(),
+            _ => format!("sub_{addr:03x}"),
+        }
+    }
+
+    /// Hot spots sorted by cycles.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+
+        writeln!(
+            out,
+            "{} cycles, {} instructions in {} frames, {:.1}% idle",
+            self.total,
+            self.instructions,
+            self.frames.len(),
+            percent(self.idle)
+        )
+        .unwrap();
+        if let (Some(min), Some(max)) = (self.frames.iter().min(), self.frames.iter().max()) {
+            let average = self.frames.iter().sum::<u64>() as f64 / self.frames.len() as f64;
+            write!(
+                out,
+                "instructions per frame: min {min} avg {average:.1} max {max}"
+            )
+            .unwrap();
+            if let Some(ipf) = self.ipf {
+                let full = self.frames.iter().filter(|&&n| n >= ipf as u64).count();
+                write!(out, " of {ipf} IPF, {full} frames used the full budget").unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        writeln!(
+            out,
+            "\nhot addresses\n{:>10} {:>6} {:>10}  addr  instruction",
+            "cycles", "%", "count"
+        )
+        .unwrap();
+        let mut addresses: Vec<(usize, u64)> = self
+            .addresses
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for &(addr, count) in addresses.iter().take(HOT_ADDRESSES) {
+            let opcode = self.opcodes[addr];
+            let text = disassemble(opcode).unwrap_or_else(|| "???".to_string());
+            let label = self.symbols.describe(addr as u16).unwrap_or_default();
+            writeln!(
+                out,
+                "{count:>10} {:>5.1}% {:>10}  {addr:03x}  {opcode:04x} {text:<24} {label}",
+                percent(count),
+                self.executions[addr]
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            out,
+            "\nsubroutines\n{:>10} {:>6} {:>10} {:>6} {:>8}  name",
+            "inclusive", "%", "exclusive", "%", "calls"
+        )
+        .unwrap();
+        let mut subroutines: Vec<(usize, u64, &Subroutine)> = self
+            .subroutines
+            .iter()
+            .map(|(&addr, sub)| (addr, sub.inclusive + self.unfinished(addr), sub))
+            .collect();
+        subroutines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (addr, inclusive, sub) in subroutines {
+            writeln!(
+                out,
+                "{:>10} {:>5.1}% {:>10} {:>5.1}% {:>8}  {}",
+                inclusive,
+                percent(inclusive),
+                sub.exclusive,
+                percent(sub.exclusive),
+                sub.calls,
+                self.name(addr)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    // cycles since entering `addr` for subroutines that haven't returned yet
+    fn unfinished(&self, addr: usize) -> u64 {
+        if addr == OFFSET && self.calls.iter().all(|&(sub, _)| sub != OFFSET) {
+            return self.total;
+        }
+        self.calls
+            .iter()
+            .find(|&&(sub, _)| sub == addr)
+            .map_or(0, |&(_, start)| self.total - start)
+    }
+
+    /// Call stacks in the folded format used by flame graph tools, one `a;b;c count` per line.
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| {
+                let names: Vec<String> = stack.iter().map(|&addr| self.name(addr)).collect();
+                format!("{} {count}", names.join(";"))
+            })
+            .collect();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+}
+
+impl Observer for Profiler {
+    fn before_exe(&mut self, interpreter: &Interpreter) -> bool {
+        let addr = interpreter.program_counter;
+        self.cost = opcode_at(interpreter, addr).map_or(0, |opcode| {
+            interpreter.timing.cycles(opcode, &interpreter.registers)
+        });
+        false
+    }
+
+    fn after_exe(&mut self, interpreter: &Interpreter, addr: usize, result: Result<(), ExeError>) {
+        if result.is_err() {
+            return;
+        }
+        let cost = self.cost;
+        self.total += cost;
+        self.instructions += 1;
+        self.frame_instructions += 1;
+        let opcode = opcode_at(interpreter, addr);
+        if let (Some(cycles), Some(opcode)) = (self.addresses.get_mut(addr), opcode) {
+            *cycles += cost;
+            self.executions[addr] += 1;
+            self.opcodes[addr] = opcode;
+        }
+        if interpreter.program_counter == addr {
+            self.idle += cost;
+        }
+
+        // the instruction counts towards the subroutine it's part of
+        let stack: Vec<usize> = std::iter::once(OFFSET)
+            .chain(self.calls.iter().map(|&(sub, _)| sub))
+            .collect();
+        self.subroutines
+            .entry(*stack.last().unwrap())
+            .or_default()
+            .exclusive += cost;
+        *self.stacks.entry(stack).or_default() += cost;
+
+        match opcode {
+            Some(opcode) if opcode >> 12 == 0x2 => {
+                let target = (opcode & 0x0FFF) as usize;
+                self.subroutines.entry(target).or_default().calls += 1;
+                self.calls.push((target, self.total));
+            }
+            Some(0x00EE) => {
+                if let Some((sub, start)) = self.calls.pop() {
+                    // recursive calls are only counted by the outermost one
+                    if self.calls.iter().all(|&(s, _)| s != sub) {
+                        self.subroutines.entry(sub).or_default().inclusive += self.total - start;
+                    }
+                }
+            }
+            _ => (),
+        }
+        // stay in sync with the real stack when a subroutine is left without returning
+        self.calls.truncate(interpreter.stack.len());
+    }
+
+    fn end_frame(&mut self, interpreter: &Interpreter) {
+        self.ipf = match interpreter.timing {
+            Timing::InstructionsPerFrame(ipf) => Some(ipf),
+            _ => None,
+        };
+        self.frames.push(self.frame_instructions);
+        self.frame_instructions = 0;
+    }
+}
+
+fn opcode_at(interpreter: &Interpreter, addr: usize) -> Option<u16> {
+    let bytes = interpreter.memory.get(addr..addr + 2)?;
+    Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
@@ -0,0 +1,70 @@
+/// Behaviours that differ between CHIP-8 implementations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY1`, `8XY2` and `8XY3` reset VF to 0.
+    pub vf_reset: bool,
+    /// `FX55` and `FX65` leave I pointing past the last register they touched.
+    pub memory_increment: bool,
+    /// `8XY6` and `8XYE` shift VX in place instead of storing VY shifted in VX.
+    pub shift_vx: bool,
+    /// `BNNN` jumps to NNN + VX, X being the first digit of NNN, instead of NNN + V0.
+    pub jump_vx: bool,
+    /// `FX1E` sets VF when I goes past 0xFFF, like the Amiga interpreter.
+    pub index_overflow: bool,
+    /// `DXYN` ends the frame, as the VIP waited for the vertical blank before drawing,
+    /// so no more than 60 sprites are drawn a second. Off in every preset, since most programs
+    /// run far too slowly with it; `--display-wait` turns it on.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub const CHIP8: Quirks = Quirks {
+        vf_reset: true,
+        memory_increment: true,
+        shift_vx: false,
+        jump_vx: false,
+        index_overflow: false,
+        display_wait: false,
+    };
+
+    /// SUPER-CHIP 1.1 as found on the HP 48 calculators.
+    pub const SUPERCHIP: Quirks = Quirks {
+        vf_reset: false,
+        memory_increment: false,
+        shift_vx: true,
+        jump_vx: true,
+        index_overflow: false,
+        display_wait: false,
+    };
+
+    /// Octo's XO-CHIP.
+    pub const XOCHIP: Quirks = Quirks {
+        vf_reset: false,
+        memory_increment: true,
+        shift_vx: false,
+        jump_vx: false,
+        index_overflow: false,
+        display_wait: false,
+    };
+
+    pub const PRESETS: [(&'static str, Quirks); 3] = [
+        ("chip8", Quirks::CHIP8),
+        ("superchip", Quirks::SUPERCHIP),
+        ("xochip", Quirks::XOCHIP),
+    ];
+
+    /// The preset called `name`, as listed in `PRESETS`.
+    pub fn preset(name: &str) -> Option<Quirks> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|&(_, quirks)| quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::CHIP8
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+use crate::screen;
+use crate::{Interpreter, Observer, HEIGHT, TARGET_FPS, WIDTH};
+
+/// Sample rate of the recorded sound.
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Pitch of the buzzer while the sound timer runs.
+pub const TONE: u32 = 440;
+// a quarter of full scale
+const VOLUME: i16 = i16::MAX / 4;
+// GIF delays below this many hundredths of a second are slowed down by most viewers,
+// changes that come faster are merged into the next frame
+const MIN_GIF_DELAY: u64 = 2;
+
+/// Where a `Recorder` writes to, any of them can be left out.
+#[derive(Default)]
+pub struct Outputs {
+    pub gif: Option<PathBuf>,
+    /// A directory for every frame as a numbered PNG, for `ffmpeg -framerate 60 -i %06d.png`.
+    pub frames: Option<PathBuf>,
+    pub wav: Option<PathBuf>,
+    /// Size of a CHIP-8 pixel in the GIF and the frames.
+    pub scale: usize,
+}
+
+// a GIF frame not written yet, because its delay is only known once the screen changes
+struct Pending {
+    rgba: Vec<u8>,
+    start: u64,
+}
+
+/// Records the screen and the buzzer every frame.
+pub struct Recorder {
+    scale: usize,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+    pending: Option<Pending>,
+    frames: Option<PathBuf>,
+    wav: Option<hound::WavWriter<BufWriter<File>>>,
+    // sample number, which keeps the square wave's phase across frames
+    sample: u64,
+    frame: u64,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    pub fn start(outputs: &Outputs) -> io::Result<Self> {
+        let scale = outputs.scale.max(1);
+        let gif = match &outputs.gif {
+            Some(path) => {
+                let file = BufWriter::new(File::create(path)?);
+                let (width, height) = ((WIDTH * scale) as u16, (HEIGHT * scale) as u16);
+                let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(gif_error)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(gif_error)?;
+                Some(encoder)
+            }
+            None => None,
+        };
+        if let Some(dir) = &outputs.frames {
+            std::fs::create_dir_all(dir)?;
+        }
+        let wav = match &outputs.wav {
+            Some(path) => {
+                let spec = hound::WavSpec {
+                    channels: 1,
+                    sample_rate: SAMPLE_RATE,
+                    bits_per_sample: 16,
+                    sample_format: hound::SampleFormat::Int,
+                };
+                Some(hound::WavWriter::create(path, spec).map_err(wav_error)?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            scale,
+            gif,
+            pending: None,
+            frames: outputs.frames.clone(),
+            wav,
+            sample: 0,
+            frame: 0,
+            error: None,
+        })
+    }
+
+    /// Frames recorded so far.
+    pub fn frames(&self) -> u64 {
+        self.frame
+    }
+
+    /// Add the interpreter's screen and sound for one frame.
+    pub fn record(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        let rgba = screen::render(interpreter, self.scale);
+        let (width, height) = (WIDTH * self.scale, HEIGHT * self.scale);
+
+        if let Some(dir) = &self.frames {
+            let path = dir.join(format!("{:06}.png", self.frame));
+            let file = BufWriter::new(File::create(path)?);
+            screen::encode_png(&rgba, width, height, 1, file)?;
+        }
+        if self.gif.is_some() {
+            self.add_gif_frame(rgba)?;
+        }
+        if let Some(wav) = &mut self.wav {
+            let on = interpreter.buzzing();
+            let end = (self.frame + 1) * SAMPLE_RATE as u64 / TARGET_FPS;
+            for sample in self.sample..end {
+                wav.write_sample(if on { buzzer(sample) } else { 0 })
+                    .map_err(wav_error)?;
+            }
+            self.sample = end;
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn add_gif_frame(&mut self, rgba: Vec<u8>) -> io::Result<()> {
+        match &mut self.pending {
+            Some(pending) if pending.rgba == rgba => return Ok(()),
+            // too soon after the last change to be shown on its own
+            Some(pending)
+                if centiseconds(self.frame) - centiseconds(pending.start) < MIN_GIF_DELAY =>
+            {
+                pending.rgba = rgba;
+                return Ok(());
+            }
+            _ => (),
+        }
+        self.write_pending()?;
+        self.pending = Some(Pending {
+            rgba,
+            start: self.frame,
+        });
+        Ok(())
+    }
+
+    // write the pending GIF frame, shown until the current frame
+    fn write_pending(&mut self) -> io::Result<()> {
+        let (Some(gif), Some(pending)) = (&mut self.gif, self.pending.take()) else {
+            return Ok(());
+        };
+        let delay = centiseconds(self.frame) - centiseconds(pending.start);
+        let mut frame = indexed_frame(pending.rgba, self.scale);
+        frame.delay = delay.max(1) as u16;
+        gif.write_frame(&frame).map_err(gif_error)
+    }
+
+    /// Write what is left and close the files.
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.write_pending()?;
+        if let Some(wav) = self.wav.take() {
+            wav.finalize().map_err(wav_error)?;
+        }
+        drop(self.gif.take());
+        Ok(())
+    }
+}
+
+impl Observer for Recorder {
+    fn end_frame(&mut self, interpreter: &Interpreter) {
+        if self.error.is_none() {
+            self.error = self.record(interpreter).err();
+        }
+    }
+}
+
+/// The buzzer's square wave at sample number `sample`, at `SAMPLE_RATE`.
+pub fn buzzer(sample: u64) -> i16 {
+    // the first half of every period is high
+    if (sample * TONE as u64 * 2 / SAMPLE_RATE as u64).is_multiple_of(2) {
+        VOLUME
+    } else {
+        -VOLUME
+    }
+}
+
+// time of a 60 Hz frame in hundredths of a second, what GIF delays are counted in
+fn centiseconds(frame: u64) -> u64 {
+    frame * 100 / TARGET_FPS
+}
+
+// a scaled up frame, with a palette of the colors in the frame when there are few enough
+fn indexed_frame(mut rgba: Vec<u8>, scale: usize) -> gif::Frame<'static> {
+    let (width, height) = ((WIDTH * scale) as u16, (HEIGHT * scale) as u16);
+    let mut palette = Vec::new();
+    let mut indices = HashMap::new();
+    let mut pixels = Vec::with_capacity(rgba.len() / 4);
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let next = indices.len();
+        let index = *indices.entry(color).or_insert(next);
+        if index == next {
+            palette.extend_from_slice(&color);
+        }
+        pixels.push(index as u8);
+    }
+    if indices.len() > 256 {
+        return gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+    }
+    gif::Frame::from_palette_pixels(width, height, pixels, palette, None)
+}
+
+fn gif_error(err: gif::EncodingError) -> io::Error {
+    match err {
+        gif::EncodingError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+fn wav_error(err: hound::Error) -> io::Error {
+    match err {
+        hound::Error::IoError(err) => err,
+        err => io::Error::other(err),
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Frames run back to back when the host falls behind, time for more than this is dropped.
+/// Faster speeds allow proportionally more.
+pub const MAX_CATCH_UP: u64 = 5;
+
+/// How fast emulated time, instructions and timers alike, runs against the host clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Speed {
+    Quarter,
+    Half,
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+    /// As many frames as the host can run.
+    Unlimited,
+}
+
+impl Speed {
+    const ALL: [Speed; 6] = [
+        Speed::Quarter,
+        Speed::Half,
+        Speed::Normal,
+        Speed::Double,
+        Speed::Quadruple,
+        Speed::Unlimited,
+    ];
+
+    /// The next speed up, `Unlimited` being the last.
+    pub fn faster(self) -> Self {
+        Self::ALL[(self as usize + 1).min(Self::ALL.len() - 1)]
+    }
+
+    /// The next speed down, `Quarter` being the last.
+    pub fn slower(self) -> Self {
+        Self::ALL[(self as usize).saturating_sub(1)]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Speed::Quarter => "0.25X",
+            Speed::Half => "0.5X",
+            Speed::Normal => "1X",
+            Speed::Double => "2X",
+            Speed::Quadruple => "4X",
+            Speed::Unlimited => "MAX",
+        }
+    }
+
+    // emulated time per host time in quarters, `None` when unlimited
+    fn quarters(self) -> Option<u128> {
+        match self {
+            Speed::Quarter => Some(1),
+            Speed::Half => Some(2),
+            Speed::Normal => Some(4),
+            Speed::Double => Some(8),
+            Speed::Quadruple => Some(16),
+            Speed::Unlimited => None,
+        }
+    }
+}
+
+/// Decides when emulated frames run, at a fixed rate measured from the host clock rather than
+/// from when the event loop happens to wake up. Frames that fell behind are run together and
+/// drawn once, up to `MAX_CATCH_UP` of them.
+pub struct Scheduler {
+    rate: u128,
+    speed: Speed,
+    last: Instant,
+    // emulated time not yet spent on frames, in nanoseconds times the rate times 4,
+    // so that no rounding builds up at any speed
+    lag: u128,
+    dropped: u64,
+}
+
+impl Scheduler {
+    /// A scheduler running `rate` frames per second from `now`.
+    pub fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            rate: rate as u128,
+            speed: Speed::Normal,
+            last: now,
+            lag: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    /// How many frames are due by `now`. At unlimited speed that is `u64::MAX`, and the caller
+    /// runs frames for as long as it can spare.
+    pub fn frames_due(&mut self, now: Instant) -> u64 {
+        let elapsed = now.saturating_duration_since(self.last);
+        self.last = now;
+        let Some(quarters) = self.speed.quarters() else {
+            return u64::MAX;
+        };
+        self.lag += elapsed.as_nanos() * self.rate * quarters;
+
+        let due = (self.lag / (4 * NANOS_PER_SECOND)) as u64;
+        self.lag %= 4 * NANOS_PER_SECOND;
+        let limit = MAX_CATCH_UP * (quarters as u64 / 4).max(1);
+        if due > limit {
+            self.dropped += due - limit;
+            return limit;
+        }
+        due
+    }
+
+    /// When the next frame is due, for the event loop to wait until.
+    pub fn next_frame(&self) -> Instant {
+        let Some(quarters) = self.speed.quarters() else {
+            return self.last;
+        };
+        let remaining = (4 * NANOS_PER_SECOND - self.lag).div_ceil(self.rate * quarters);
+        self.last + Duration::from_nanos(remaining as u64)
+    }
+
+    /// Frames skipped because the host couldn't keep up.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
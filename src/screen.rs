@@ -0,0 +1,118 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{filter, Interpreter, HEIGHT, WIDTH};
+
+pub type Screen = [[bool; WIDTH]; HEIGHT];
+
+/// One line per row, `#` for pixels that are on and `.` for pixels that are off.
+pub fn to_text(screen: &Screen) -> String {
+    let mut text = String::with_capacity((WIDTH + 1) * HEIGHT);
+    for row in screen {
+        text.extend(row.iter().map(|&on| if on { '#' } else { '.' }));
+        text.push('\n');
+    }
+    text
+}
+
+/// Write the frame `Interpreter::draw` draws, in its colors and with its filters, as a PNG with
+/// every CHIP-8 pixel `scale` x `scale` pixels large.
+pub fn write_png(interpreter: &Interpreter, path: &Path, scale: usize) -> io::Result<()> {
+    let scale = scale.max(1);
+    let frame = render(interpreter, scale);
+    let file = io::BufWriter::new(std::fs::File::create(path)?);
+    encode_png(&frame, WIDTH * scale, HEIGHT * scale, 1, file)
+}
+
+/// The frame `Interpreter::draw` draws, scaled up `scale` times and run through the
+/// interpreter's filters.
+pub fn render(interpreter: &Interpreter, scale: usize) -> Vec<u8> {
+    let mut frame = vec![0; WIDTH * HEIGHT * 4];
+    interpreter.draw(&mut frame);
+    let size = (WIDTH * scale, HEIGHT * scale);
+    let mut scaled = vec![0; size.0 * size.1 * 4];
+    filter::render(
+        &frame,
+        (WIDTH, HEIGHT),
+        &mut scaled,
+        size,
+        &interpreter.filters,
+    );
+    scaled
+}
+
+/// Encode an RGBA `frame` of `width` x `height` pixels as a PNG, scaled up `scale` times.
+pub fn encode_png(
+    frame: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+    writer: impl io::Write,
+) -> io::Result<()> {
+    let scale = scale.max(1);
+    let data = scale_rgba(frame, width, scale);
+
+    let mut encoder = png::Encoder::new(writer, (width * scale) as u32, (height * scale) as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Scale an RGBA `frame` `width` pixels wide up `scale` times.
+pub fn scale_rgba(frame: &[u8], width: usize, scale: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(frame.len() * scale * scale);
+    for row in frame.chunks_exact(width * 4) {
+        let mut scaled = Vec::with_capacity(row.len() * scale);
+        for pixel in row.chunks_exact(4) {
+            for _ in 0..scale {
+                scaled.extend_from_slice(pixel);
+            }
+        }
+        for _ in 0..scale {
+            data.extend_from_slice(&scaled);
+        }
+    }
+    data
+}
+
+/// `<rom name>-<YYYYMMDD>-<HHMMSS>.<milliseconds>.<extension>` in `dir`, the time in UTC,
+/// for screenshots and recordings.
+pub fn capture_path(dir: &Path, rom: &str, time: SystemTime, extension: &str) -> PathBuf {
+    let name = Path::new(rom)
+        .file_stem()
+        .map_or("screenshot".into(), |stem| stem.to_string_lossy());
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_date(seconds / 86400);
+    let time_of_day = seconds % 86400;
+    dir.join(format!(
+        "{name}-{year:04}{month:02}{day:02}-{:02}{:02}{:02}.{:03}.{extension}",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis()
+    ))
+}
+
+// year, month and day of the days since 1970-01-01, from Howard Hinnant's `civil_from_days`
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
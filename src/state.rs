@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::{Interpreter, KeyState, KeyStatus, KeypadKey, HEIGHT, STACK_SIZE, WIDTH};
+
+const MAGIC: &[u8; 4] = b"C8SV";
+const VERSION: u8 = 1;
+
+/// Why `Interpreter::load_state` couldn't load a state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    NotAState,
+    Version(u8),
+    Truncated,
+    Invalid,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotAState => write!(f, "not a save state"),
+            Self::Version(version) => write!(f, "save state version {version} is not supported"),
+            Self::Truncated => write!(f, "save state is cut short"),
+            Self::Invalid => write!(f, "save state is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl Interpreter {
+    /// Everything that changes while a program runs, for `load_state`. The quirks, timing,
+    /// palette, persistence and filters are settings and aren't saved.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.push(VERSION);
+        data.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&self.memory);
+        data.extend(self.screen.iter().flatten().map(|&lit| lit as u8));
+        data.extend_from_slice(&(self.program_counter as u16).to_le_bytes());
+        data.extend_from_slice(&self.index.to_le_bytes());
+        data.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        for addr in &self.stack {
+            data.extend_from_slice(&addr.to_le_bytes());
+        }
+        data.extend_from_slice(&[self.delay_timer, self.sound_timer]);
+        data.extend_from_slice(&self.registers);
+        data.push(self.halt as u8);
+        for key in &self.keys {
+            data.extend_from_slice(&[
+                key.pressed_frames_ago,
+                key.released_frames_ago,
+                key.down as u8,
+            ]);
+        }
+        data.extend_from_slice(&match self.key_wait_status {
+            KeyStatus::NoKeyAwait => [0, 0],
+            KeyStatus::KeyAwait => [1, 0],
+            KeyStatus::KeyConf(key) => [2, key as u8],
+        });
+        for brightness in &self.brightness {
+            data.extend_from_slice(&brightness.to_le_bytes());
+        }
+        data.push(self.vblank_wait as u8);
+        data.extend_from_slice(&self.budget.to_le_bytes());
+        data
+    }
+
+    /// Restore a state saved by `save_state`, leaving the interpreter as it was when that
+    /// fails. Bytes after the state are ignored, so it can be read from a larger buffer.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { data };
+        if reader.take(4).ok() != Some(MAGIC.as_slice()) {
+            return Err(StateError::NotAState);
+        }
+        match reader.u8()? {
+            VERSION => (),
+            version => return Err(StateError::Version(version)),
+        }
+
+        let memory_len = u32::from_le_bytes(reader.array()?) as usize;
+        if memory_len != self.memory.len() {
+            return Err(StateError::Invalid);
+        }
+        let memory = reader.take(memory_len)?;
+        let mut screen = [[false; WIDTH]; HEIGHT];
+        for row in &mut screen {
+            for pixel in row {
+                *pixel = reader.bool()?;
+            }
+        }
+        let program_counter = reader.u16()? as usize;
+        let index = reader.u16()?;
+        let stack_len = reader.u16()?;
+        if stack_len as usize > STACK_SIZE {
+            return Err(StateError::Invalid);
+        }
+        let stack = (0..stack_len)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let [delay_timer, sound_timer] = reader.array()?;
+        let registers = reader.array()?;
+        let halt = reader.bool()?;
+        let mut keys = [KeyState::new(); 16];
+        for key in &mut keys {
+            let [pressed_frames_ago, released_frames_ago] = reader.array()?;
+            *key = KeyState {
+                pressed_frames_ago,
+                released_frames_ago,
+                down: reader.bool()?,
+            };
+        }
+        let key_wait_status = match reader.array()? {
+            [0, _] => KeyStatus::NoKeyAwait,
+            [1, _] => KeyStatus::KeyAwait,
+            [2, digit] => {
+                KeyStatus::KeyConf(KeypadKey::from_digit(digit).ok_or(StateError::Invalid)?)
+            }
+            _ => return Err(StateError::Invalid),
+        };
+        let brightness = (0..WIDTH * HEIGHT)
+            .map(|_| reader.array().map(f32::from_le_bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let vblank_wait = reader.bool()?;
+        let budget = i64::from_le_bytes(reader.array()?);
+
+        // in place, so pointers to memory handed out stay valid
+        self.memory.copy_from_slice(memory);
+        self.screen = screen;
+        self.program_counter = program_counter;
+        self.index = index;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.registers = registers;
+        self.halt = halt;
+        self.keys = keys;
+        self.key_wait_status = key_wait_status;
+        self.brightness = brightness;
+        self.vblank_wait = vblank_wait;
+        self.budget = budget;
+        Ok(())
+    }
+}
+
+// reads a state front to back
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Labels and source lines of an assembled ROM, written next to it as a `.sym` file.
+///
+/// ```text
+/// source game.8o
+/// label 0x200 main
+/// line 0x200 4
+/// ```
+#[derive(Default)]
+pub struct SymbolMap {
+    pub source: String,
+    labels: BTreeMap<String, u16>,
+    lines: BTreeMap<u16, usize>,
+}
+
+impl SymbolMap {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn add_label(&mut self, addr: u16, name: &str) {
+        self.labels.insert(name.to_string(), addr);
+    }
+
+    pub fn add_line(&mut self, addr: u16, line: usize) {
+        self.lines.entry(addr).or_insert(line);
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let addr = |s: &str| {
+                u16::from_str_radix(s.trim_start_matches("0x"), 16)
+                    .map_err(|_| format!("line {}: bad address '{s}'", i + 1))
+            };
+            match fields[..] {
+                [] => (),
+                ["source", ref name @ ..] => map.source = name.join(" "),
+                ["label", a, name] => map.add_label(addr(a)?, name),
+                ["line", a, n] => map.add_line(
+                    addr(a)?,
+                    n.parse()
+                        .map_err(|_| format!("line {}: bad line number '{n}'", i + 1))?,
+                ),
+                _ => return Err(format!("line {}: unrecognised entry '{line}'", i + 1)),
+            }
+        }
+        Ok(map)
+    }
+
+    /// Address of a label.
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// `addr` relative to the closest label at or before it, e.g. `main+0x4`.
+    pub fn describe(&self, addr: u16) -> Option<String> {
+        let closest = self
+            .labels
+            .iter()
+            .filter(|(_, &base)| base <= addr)
+            .max_by_key(|(_, &base)| base);
+        closest.map(|(name, &base)| match addr - base {
+            0 => name.clone(),
+            offset => format!("{name}+0x{offset:x}"),
+        })
+    }
+
+    /// Source location of the statement assembled at `addr`, e.g. `game.8o:12`.
+    pub fn location(&self, addr: u16) -> Option<String> {
+        let line = self.lines.get(&addr)?;
+        Some(format!("{}:{line}", self.source))
+    }
+}
+
+impl fmt::Display for SymbolMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "source {}", self.source)?;
+        let mut labels: Vec<_> = self.labels.iter().collect();
+        labels.sort_by_key(|&(name, addr)| (addr, name));
+        for (name, addr) in labels {
+            writeln!(f, "label 0x{addr:03x} {name}")?;
+        }
+        for (addr, line) in &self.lines {
+            writeln!(f, "line 0x{addr:03x} {line}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{IPF, TARGET_FPS};
+
+/// Machine cycles of the VIP's CDP1802 in a 60 Hz frame, 8 clock cycles each at 1.7609 MHz.
+const VIP_CYCLES_PER_FRAME: i64 = 3668;
+/// Cycles the CDP1861 takes from every frame for DMA, 8 bytes for each of the 128 lines shown,
+/// and the interrupt routine that sets it up and counts the timers down.
+const VIP_DISPLAY_CYCLES: i64 = 128 * 8 + 46;
+
+/// How many instructions `Interpreter::update` runs in a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    /// The same number of instructions every frame.
+    InstructionsPerFrame(usize),
+    /// A number of instructions every second, spread evenly over the frames.
+    InstructionsPerSecond(u32),
+    /// As many instructions as the COSMAC VIP ran in a frame, each costing the machine cycles
+    /// the original interpreter took for it.
+    Vip,
+}
+
+impl Timing {
+    /// Parse `ipf:<n>`, `ips:<n>` or `vip`.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.split_once(':') {
+            Some(("ipf", n)) => n.parse().ok().map(Timing::InstructionsPerFrame),
+            Some(("ips", n)) => n.parse().ok().map(Timing::InstructionsPerSecond),
+            None if text == "vip" => Some(Timing::Vip),
+            _ => None,
+        }
+    }
+
+    /// What a frame adds to the budget instructions are paid from.
+    pub(crate) fn frame_budget(&self) -> i64 {
+        match *self {
+            Timing::InstructionsPerFrame(n) => n as i64,
+            Timing::InstructionsPerSecond(n) => n as i64,
+            Timing::Vip => VIP_CYCLES_PER_FRAME - VIP_DISPLAY_CYCLES,
+        }
+    }
+
+    /// What executing `opcode` takes from the budget.
+    pub(crate) fn cost(&self, opcode: Option<u16>, registers: &[u8; 16]) -> i64 {
+        match self {
+            Timing::InstructionsPerFrame(_) => 1,
+            // a second's worth of budget is spread over TARGET_FPS frames
+            Timing::InstructionsPerSecond(_) => TARGET_FPS as i64,
+            Timing::Vip => opcode.map_or(0, |opcode| vip_cycles(opcode, registers)),
+        }
+    }
+
+    /// Cycles `opcode` takes, for the profiler: the VIP's machine cycles, or one for the modes
+    /// that count instructions.
+    pub fn cycles(&self, opcode: u16, registers: &[u8; 16]) -> u64 {
+        match self {
+            Timing::Vip => vip_cycles(opcode, registers) as u64,
+            _ => 1,
+        }
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::InstructionsPerFrame(IPF)
+    }
+}
+
+/// Machine cycles the VIP interpreter's main loop takes to fetch and decode every instruction
+/// before jumping to the routine for it.
+const VIP_FETCH_CYCLES: i64 = 68;
+
+/// Machine cycles the VIP interpreter takes to fetch, decode and execute `opcode`.
+///
+/// The fetch is [`VIP_FETCH_CYCLES`] for every instruction; what each routine takes after it is
+/// from the instruction timing table in Jackson Sommerich's "Chip-8 Instruction Scheduling and
+/// Frequency" (2019), counted from the interpreter listing in Laurence Scotford's "Chip-8 on the
+/// COSMAC VIP". Costs that depend on operands are computed from `registers` before the
+/// instruction runs.
+pub fn vip_cycles(opcode: u16, registers: &[u8; 16]) -> i64 {
+    VIP_FETCH_CYCLES + vip_execute_cycles(opcode, registers)
+}
+
+fn vip_execute_cycles(opcode: u16, registers: &[u8; 16]) -> i64 {
+    let x = (opcode >> 8 & 0xF) as usize;
+    let n = (opcode & 0xF) as i64;
+    match opcode >> 12 {
+        0x0 if opcode == 0x00E0 => 24,
+        0x0 if opcode == 0x00EE => 10,
+        0x1 | 0x3 | 0x4 | 0xA => 12,
+        0x2 => 26,
+        0x5 | 0x9 | 0xE => 16,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0xB => 22,
+        0xC => 36,
+        0xD => {
+            // sprites not on a byte boundary are shifted a bit at a time and written to two bytes
+            let shift = (registers[x] % 8) as i64;
+            let row = if shift == 0 { 14 } else { 22 + 4 * shift };
+            26 + n * row
+        }
+        0xF => match opcode & 0xFF {
+            0x07 | 0x0A | 0x15 | 0x18 => 10,
+            0x1E => 19,
+            0x29 => 20,
+            // each digit is found by repeated subtraction
+            0x33 => {
+                let value = registers[x];
+                let digits = value / 100 + value / 10 % 10 + value % 10;
+                84 + 12 * digits as i64
+            }
+            0x55 | 0x65 => 14 + 14 * (x as i64 + 1),
+            _ => 12,
+        },
+        _ => 12,
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use crate::disasm::disassemble;
+use crate::{ExeError, Interpreter, Observer};
+
+/// Which executed instructions end up in the trace.
+#[derive(Default)]
+pub struct TraceFilter {
+    pub addresses: Option<RangeInclusive<u64>>,
+    /// Opcode classes, the first hex digit of the opcode.
+    pub classes: Option<Vec<u8>>,
+    pub frames: Option<RangeInclusive<u64>>,
+}
+
+impl TraceFilter {
+    fn matches(&self, frame: u64, addr: usize, opcode: Option<u16>) -> bool {
+        self.addresses
+            .as_ref()
+            .is_none_or(|r| r.contains(&(addr as u64)))
+            && self.frames.as_ref().is_none_or(|r| r.contains(&frame))
+            && self.classes.as_ref().is_none_or(|classes| {
+                opcode.is_some_and(|opcode| classes.contains(&((opcode >> 12) as u8)))
+            })
+    }
+}
+
+/// Parse `start-end` (or a single value) in the given radix.
+pub fn parse_range(text: &str, radix: u32) -> Option<RangeInclusive<u64>> {
+    let parse = |s: &str| u64::from_str_radix(s.trim_start_matches("0x"), radix).ok();
+    match text.split_once('-') {
+        Some((start, end)) => Some(parse(start)?..=parse(end)?),
+        None => {
+            let value = parse(text)?;
+            Some(value..=value)
+        }
+    }
+}
+
+/// Parse a comma separated list of opcode classes, e.g. `8,d,f`.
+pub fn parse_classes(text: &str) -> Option<Vec<u8>> {
+    text.split(',')
+        .map(|class| u8::from_str_radix(class, 16).ok().filter(|&c| c <= 0xF))
+        .collect()
+}
+
+// state the register deltas are computed against
+#[derive(Default)]
+struct Snapshot {
+    registers: [u8; 16],
+    index: u16,
+    stack_len: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl Snapshot {
+    fn of(interpreter: &Interpreter) -> Self {
+        Self {
+            registers: interpreter.registers,
+            index: interpreter.index,
+            stack_len: interpreter.stack.len(),
+            delay_timer: interpreter.delay_timer,
+            sound_timer: interpreter.sound_timer,
+        }
+    }
+}
+
+/// Writes every executed instruction with the registers it changed,
+/// or keeps only the last few and writes them when `exe` fails.
+pub struct Tracer {
+    output: Box<dyn Write>,
+    filter: TraceFilter,
+    // capacity and contents of the ring buffer, when only the last instructions are kept
+    ring: Option<(usize, VecDeque<String>)>,
+    frame: u64,
+    before: Snapshot,
+}
+
+impl Tracer {
+    /// Trace to `output`, or to stderr when it's `None`.
+    /// With `ring_size`, only the last `ring_size` instructions are written, once an error occurs.
+    pub fn new(
+        output: Option<&str>,
+        filter: TraceFilter,
+        ring_size: Option<usize>,
+    ) -> io::Result<Self> {
+        let output: Box<dyn Write> = match output {
+            Some(path) => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+            None => Box::new(io::stderr()),
+        };
+        Ok(Self {
+            output,
+            filter,
+            ring: ring_size.map(|size| (size, VecDeque::with_capacity(size))),
+            frame: 0,
+            before: Snapshot::default(),
+        })
+    }
+
+    fn entry(&self, interpreter: &Interpreter, addr: usize) -> String {
+        let opcode = opcode_at(interpreter, addr);
+        let text = opcode
+            .and_then(disassemble)
+            .unwrap_or_else(|| "???".to_string());
+        let mut entry = match opcode {
+            Some(opcode) => format!("{:>6} {addr:03x}: {opcode:04x} {text:<24}", self.frame),
+            None => format!("{:>6} {addr:03x}: ???? {text:<24}", self.frame),
+        };
+
+        let before = &self.before;
+        for (i, (old, new)) in before
+            .registers
+            .iter()
+            .zip(interpreter.registers)
+            .enumerate()
+        {
+            if *old != new {
+                write!(entry, " v{i:x} {old:02x}->{new:02x}").unwrap();
+            }
+        }
+        if before.index != interpreter.index {
+            write!(entry, " i {:03x}->{:03x}", before.index, interpreter.index).unwrap();
+        }
+        if interpreter.program_counter != addr + 2 {
+            write!(entry, " pc ->{:03x}", interpreter.program_counter).unwrap();
+        }
+        if before.stack_len != interpreter.stack.len() {
+            write!(
+                entry,
+                " sp {}->{}",
+                before.stack_len,
+                interpreter.stack.len()
+            )
+            .unwrap();
+        }
+        if before.delay_timer != interpreter.delay_timer {
+            let (old, new) = (before.delay_timer, interpreter.delay_timer);
+            write!(entry, " dt {old:02x}->{new:02x}").unwrap();
+        }
+        if before.sound_timer != interpreter.sound_timer {
+            let (old, new) = (before.sound_timer, interpreter.sound_timer);
+            write!(entry, " st {old:02x}->{new:02x}").unwrap();
+        }
+        entry
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if let Err(err) = writeln!(self.output, "{line}") {
+            eprintln!("trace write error: {err}");
+        }
+    }
+}
+
+impl Observer for Tracer {
+    fn before_exe(&mut self, interpreter: &Interpreter) -> bool {
+        self.before = Snapshot::of(interpreter);
+        false
+    }
+
+    fn after_exe(&mut self, interpreter: &Interpreter, addr: usize, result: Result<(), ExeError>) {
+        let opcode = opcode_at(interpreter, addr);
+        if self.filter.matches(self.frame, addr, opcode) {
+            let entry = self.entry(interpreter, addr);
+            match &mut self.ring {
+                Some((size, ring)) => {
+                    ring.push_back(entry);
+                    if ring.len() > *size {
+                        ring.pop_front();
+                    }
+                }
+                None => self.write_line(&entry),
+            }
+        }
+
+        if let Err(err) = result {
+            if let Some((_, ring)) = &mut self.ring {
+                let entries: Vec<String> = ring.drain(..).collect();
+                for entry in entries {
+                    self.write_line(&entry);
+                }
+            }
+            self.write_line(&format!("error: {err}"));
+            let _ = self.output.flush();
+        }
+    }
+
+    fn end_frame(&mut self, _interpreter: &Interpreter) {
+        self.frame += 1;
+    }
+}
+
+fn opcode_at(interpreter: &Interpreter, addr: usize) -> Option<u16> {
+    let bytes = interpreter.memory.get(addr..addr + 2)?;
+    Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
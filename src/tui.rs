@@ -0,0 +1,261 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+
+use crate::debugger::Debugger;
+use crate::disasm::disassemble;
+use crate::headless;
+use crate::scheduler::{Scheduler, Speed};
+use crate::screen::Screen;
+use crate::{get_key, Interpreter, KeypadKey, HEIGHT, WIDTH};
+
+/// Lines of text the screen takes, two rows of pixels to a line.
+pub const ROWS: usize = HEIGHT / 2;
+/// Frames a key stays down after the terminal last reported it, for terminals that only report
+/// presses. They repeat the press while a key is held, after a delay this bridges most of.
+pub const HOLD_FRAMES: u8 = 15;
+
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// The screen as half blocks, for terminals without colors.
+pub fn half_blocks(screen: &Screen) -> Vec<String> {
+    screen
+        .chunks_exact(2)
+        .map(|rows| {
+            rows[0]
+                .iter()
+                .zip(&rows[1])
+                .map(|pair| match pair {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Queue the screen with its top left at column `x` and line `y`, every character an upper
+/// half block in the color of the pixel above on the color of the pixel below, so that the
+/// palette and persistence show as in the window.
+pub fn draw_screen(
+    out: &mut impl Write,
+    interpreter: &Interpreter,
+    x: u16,
+    y: u16,
+) -> io::Result<()> {
+    let mut colors = None;
+    for line in 0..ROWS {
+        queue!(out, MoveTo(x, y + line as u16))?;
+        for column in 0..WIDTH {
+            let pair = (
+                interpreter.pixel_rgba(column, line * 2),
+                interpreter.pixel_rgba(column, line * 2 + 1),
+            );
+            // only changes are sent, which keeps frames small over a slow connection
+            if colors != Some(pair) {
+                queue!(
+                    out,
+                    SetForegroundColor(color(pair.0)),
+                    SetBackgroundColor(color(pair.1))
+                )?;
+                colors = Some(pair);
+            }
+            queue!(out, Print('▀'))?;
+        }
+    }
+    queue!(out, ResetColor)
+}
+
+fn color([r, g, b, _]: [u8; 4]) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// Lines showing the registers, timers, stack, held keys and the next instructions.
+pub fn panel(interpreter: &Interpreter) -> Vec<String> {
+    let mut lines: Vec<String> = interpreter
+        .registers
+        .chunks(4)
+        .enumerate()
+        .map(|(i, values)| {
+            let text: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(j, v)| format!("V{:X} {v:02X}", i * 4 + j))
+                .collect();
+            text.join("  ")
+        })
+        .collect();
+    lines.push(format!(
+        "I {:03X}  PC {:03X}  SP {}",
+        interpreter.index,
+        interpreter.program_counter,
+        interpreter.stack.len()
+    ));
+    lines.push(format!(
+        "DT {:02X}  ST {:02X}",
+        interpreter.delay_timer, interpreter.sound_timer
+    ));
+    let stack: Vec<String> = interpreter
+        .stack
+        .iter()
+        .map(|a| format!("{a:03X}"))
+        .collect();
+    lines.push(format!("STACK {}", stack.join(" ")).trim_end().to_string());
+
+    for (i, row) in KEYPAD.iter().enumerate() {
+        let keys: Vec<String> = row
+            .iter()
+            .map(|&key| {
+                if interpreter.keys[key as usize].is_down() {
+                    format!("{key:X}")
+                } else {
+                    "·".to_string()
+                }
+            })
+            .collect();
+        let label = if i == 0 { "KEYS" } else { "" };
+        lines.push(format!("{label:<6}{}", keys.join(" ")));
+    }
+
+    lines.push(String::new());
+    for i in 0..5 {
+        let addr = interpreter.program_counter + i * 2;
+        let Some(bytes) = interpreter.memory.get(addr..addr + 2) else {
+            break;
+        };
+        let opcode = (bytes[0] as u16) << 8 | bytes[1] as u16;
+        let text = disassemble(opcode).unwrap_or_else(|| "???".to_string());
+        let marker = if i == 0 { ">" } else { " " };
+        lines.push(format!("{marker}{addr:03X} {opcode:04X} {text}"));
+    }
+    lines
+}
+
+/// The keypad key for a character typed in the terminal, in the layout of `get_key`.
+pub fn keypad_key(c: char) -> Option<KeypadKey> {
+    get_key(&c.to_lowercase().to_string())
+}
+
+/// Keys held down by a terminal, released when it reports that or, for terminals that can't,
+/// `HOLD_FRAMES` after the last press.
+pub struct HeldKeys {
+    hold: Option<u8>,
+    frames_left: [u8; 16],
+}
+
+impl HeldKeys {
+    /// Keys released only by `release` when `reports_release` is set, and otherwise by time.
+    pub fn new(reports_release: bool) -> Self {
+        Self {
+            hold: (!reports_release).then_some(HOLD_FRAMES),
+            frames_left: [0; 16],
+        }
+    }
+
+    /// Press `key`, again for every repeat while it's held.
+    pub fn press(&mut self, interpreter: &mut Interpreter, key: KeypadKey) {
+        interpreter.press_key(key);
+        self.frames_left[key as usize] = self.hold.unwrap_or(u8::MAX);
+    }
+
+    pub fn release(&mut self, interpreter: &mut Interpreter, key: KeypadKey) {
+        if self.frames_left[key as usize] > 0 {
+            self.frames_left[key as usize] = 0;
+            interpreter.release_key(key);
+        }
+    }
+
+    /// Count down one frame, releasing the keys pressed too long ago.
+    pub fn end_frame(&mut self, interpreter: &mut Interpreter) {
+        if self.hold.is_none() {
+            return;
+        }
+        for digit in 0..16 {
+            let frames_left = &mut self.frames_left[digit as usize];
+            if *frames_left == 0 {
+                continue;
+            }
+            *frames_left -= 1;
+            if *frames_left == 0 {
+                if let Some(key) = KeypadKey::from_digit(digit) {
+                    interpreter.release_key(key);
+                }
+            }
+        }
+    }
+}
+
+/// What the event loop does after a function key, besides what the key changed itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyResult {
+    /// Run one frame while paused.
+    pub advance: bool,
+    /// Clear the terminal before the next draw, as something drawn before goes away.
+    pub redraw: bool,
+}
+
+/// What the function keys change, other than the speed of the scheduler.
+pub struct Controls {
+    pub debugger: Debugger,
+    pub show_panel: bool,
+    /// The last error or saved file, shown under the screen.
+    pub status: String,
+}
+
+impl Controls {
+    pub fn new(debugger: Debugger, show_panel: bool) -> Self {
+        Self {
+            debugger,
+            show_panel,
+            status: String::new(),
+        }
+    }
+
+    /// Handle F`n`. Screenshots of `rom` are saved in the current directory.
+    pub fn function_key(
+        &mut self,
+        n: u8,
+        interpreter: &mut Interpreter,
+        scheduler: &mut Scheduler,
+        rom: &str,
+    ) -> KeyResult {
+        let mut result = KeyResult::default();
+        match n {
+            1 => {
+                self.show_panel = !self.show_panel;
+                // a hidden panel's text is left on the terminal otherwise
+                result.redraw = true;
+            }
+            2 if self.debugger.is_paused() => self.debugger.resume(interpreter),
+            2 => self.debugger.pause(),
+            3 => result.advance = self.debugger.is_paused(),
+            4 => scheduler.set_speed(Speed::Normal),
+            5 => scheduler.set_speed(scheduler.speed().slower()),
+            6 => scheduler.set_speed(scheduler.speed().faster()),
+            10 => {
+                self.debugger.pause();
+                if let Err(err) = interpreter.exe() {
+                    self.status = err.to_string();
+                }
+            }
+            12 => {
+                self.status = match headless::screenshot(interpreter, rom, Path::new("."), 1) {
+                    Ok(path) => format!("saved {}", path.display()),
+                    Err(err) => format!("could not save screenshot: {err}"),
+                }
+            }
+            _ => (),
+        }
+        result
+    }
+}
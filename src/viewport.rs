@@ -0,0 +1,57 @@
+/// How a frame is scaled up to fill the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaling {
+    /// By the largest whole number that fits, so that every pixel is the same size.
+    #[default]
+    Integer,
+    /// As large as fits while keeping the aspect ratio.
+    Fit,
+}
+
+impl Scaling {
+    /// Parse `integer` or `fit`.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "integer" => Some(Scaling::Integer),
+            "fit" => Some(Scaling::Fit),
+            _ => None,
+        }
+    }
+}
+
+/// The size a frame of `frame` size is scaled to in a window of `window` size, never smaller
+/// than the frame. The window shows it centred with bars around it.
+pub fn scaled_size(
+    frame: (usize, usize),
+    window: (usize, usize),
+    scaling: Scaling,
+) -> (usize, usize) {
+    let ((width, height), (window_width, window_height)) = (frame, window);
+    match scaling {
+        Scaling::Integer => {
+            let scale = (window_width / width).min(window_height / height).max(1);
+            (width * scale, height * scale)
+        }
+        // whichever side runs into the window's edge first sets the size
+        Scaling::Fit if window_width * height <= window_height * width => (
+            window_width.max(width),
+            (window_width * height / width).max(height),
+        ),
+        Scaling::Fit => (
+            (window_height * width / height).max(width),
+            window_height.max(height),
+        ),
+    }
+}
+
+/// Scale an RGBA frame up from `from` to `to` pixels, repeating pixels unevenly when the sizes
+/// aren't whole multiples of each other.
+pub fn scale_nearest(frame: &[u8], from: (usize, usize), out: &mut [u8], to: (usize, usize)) {
+    let columns: Vec<usize> = (0..to.0).map(|x| x * from.0 / to.0 * 4).collect();
+    for (y, row) in out.chunks_exact_mut(to.0 * 4).take(to.1).enumerate() {
+        let source = &frame[y * from.1 / to.1 * from.0 * 4..][..from.0 * 4];
+        for (pixel, &column) in row.chunks_exact_mut(4).zip(&columns) {
+            pixel.copy_from_slice(&source[column..column + 4]);
+        }
+    }
+}
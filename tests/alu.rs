@@ -0,0 +1,157 @@
+//! Every operand pair through the arithmetic in `chip8::alu`, checked against the same
+//! operations done in wider integers, and the VF cases the flags test ROM looks at.
+
+use chip8::alu::{add_index, add_value, register_op};
+use chip8::{Interpreter, Quirks};
+
+fn all_operands() -> impl Iterator<Item = (u8, u8)> {
+    (0..=255).flat_map(|vx| (0..=255).map(move |vy| (vx, vy)))
+}
+
+#[test]
+fn add_with_carry() {
+    for (vx, vy) in all_operands() {
+        let sum = vx as u16 + vy as u16;
+        let expected = (sum as u8, Some((sum > 0xFF) as u8));
+        assert_eq!(register_op(0x4, vx, vy, &Quirks::CHIP8), Some(expected));
+    }
+}
+
+#[test]
+fn subtract_with_borrow() {
+    for (vx, vy) in all_operands() {
+        let difference = vx as i16 - vy as i16;
+        let expected = (
+            difference.rem_euclid(256) as u8,
+            Some((difference >= 0) as u8),
+        );
+        assert_eq!(register_op(0x5, vx, vy, &Quirks::CHIP8), Some(expected));
+
+        let reversed = vy as i16 - vx as i16;
+        let expected = (reversed.rem_euclid(256) as u8, Some((reversed >= 0) as u8));
+        assert_eq!(register_op(0x7, vx, vy, &Quirks::CHIP8), Some(expected));
+    }
+}
+
+#[test]
+fn logic() {
+    for (vx, vy) in all_operands() {
+        for (quirks, flag) in [(Quirks::CHIP8, Some(0)), (Quirks::XOCHIP, None)] {
+            assert_eq!(register_op(0x1, vx, vy, &quirks), Some((vx | vy, flag)));
+            assert_eq!(register_op(0x2, vx, vy, &quirks), Some((vx & vy, flag)));
+            assert_eq!(register_op(0x3, vx, vy, &quirks), Some((vx ^ vy, flag)));
+        }
+        assert_eq!(register_op(0x0, vx, vy, &Quirks::CHIP8), Some((vy, None)));
+    }
+}
+
+#[test]
+fn shifts() {
+    for (vx, vy) in all_operands() {
+        for (quirks, source) in [(Quirks::CHIP8, vy), (Quirks::SUPERCHIP, vx)] {
+            let right = (source / 2, Some(source % 2));
+            assert_eq!(register_op(0x6, vx, vy, &quirks), Some(right));
+            let left = ((source as u16 * 2) as u8, Some((source >= 0x80) as u8));
+            assert_eq!(register_op(0xE, vx, vy, &quirks), Some(left));
+        }
+    }
+}
+
+#[test]
+fn undefined_operations() {
+    for n in [0x8, 0x9, 0xA, 0xB, 0xC, 0xD, 0xF] {
+        assert_eq!(register_op(n, 1, 2, &Quirks::CHIP8), None, "{n:x}");
+    }
+}
+
+#[test]
+fn add_value_wraps() {
+    for (vx, nn) in all_operands() {
+        assert_eq!(add_value(vx, nn), ((vx as u16 + nn as u16) % 256) as u8);
+    }
+}
+
+#[test]
+fn add_index_wraps() {
+    let amiga = Quirks {
+        index_overflow: true,
+        ..Quirks::CHIP8
+    };
+    assert_eq!(add_index(0x100, 0x10, &Quirks::CHIP8), (0x110, None));
+    assert_eq!(add_index(0xFFF, 0x01, &Quirks::CHIP8), (0x1000, None));
+    assert_eq!(add_index(0xFFFF, 0x02, &Quirks::CHIP8), (0x0001, None));
+    assert_eq!(add_index(0xFF0, 0x0F, &amiga), (0xFFF, Some(0)));
+    assert_eq!(add_index(0xFF0, 0x10, &amiga), (0x1000, Some(1)));
+    assert_eq!(add_index(0x1000, 0x10, &amiga), (0x1010, Some(0)));
+}
+
+// 8XYN with X or Y being VF, run by the interpreter
+fn run(opcode: u16, quirks: Quirks, registers: &[(usize, u8)]) -> [u8; 16] {
+    let mut interpreter = Interpreter::with_program(&[opcode]);
+    interpreter.quirks = quirks;
+    for &(register, value) in registers {
+        interpreter.registers[register] = value;
+    }
+    interpreter.exe().unwrap();
+    interpreter.registers
+}
+
+#[test]
+fn flag_wins_when_x_is_vf() {
+    for (_, quirks) in Quirks::PRESETS {
+        // 0xF0 + 0x20 carries
+        assert_eq!(run(0x8F14, quirks, &[(0xF, 0xF0), (1, 0x20)])[0xF], 1);
+        // 0x20 - 0x30 borrows
+        assert_eq!(run(0x8F15, quirks, &[(0xF, 0x20), (1, 0x30)])[0xF], 0);
+        assert_eq!(run(0x8F17, quirks, &[(0xF, 0x20), (1, 0x30)])[0xF], 1);
+        // 0x81 shifted either way pushes out a 1, whichever register is shifted
+        assert_eq!(run(0x8F16, quirks, &[(0xF, 0x81), (1, 0x81)])[0xF], 1);
+        assert_eq!(run(0x8F1E, quirks, &[(0xF, 0x81), (1, 0x81)])[0xF], 1);
+    }
+}
+
+#[test]
+fn logic_with_x_as_vf() {
+    assert_eq!(
+        run(0x8F11, Quirks::CHIP8, &[(0xF, 0x0C), (1, 0x03)])[0xF],
+        0
+    );
+    assert_eq!(
+        run(0x8F11, Quirks::XOCHIP, &[(0xF, 0x0C), (1, 0x03)])[0xF],
+        0x0F
+    );
+}
+
+#[test]
+fn vf_read_before_written_when_y_is_vf() {
+    for (_, quirks) in Quirks::PRESETS {
+        let registers = run(0x81F4, quirks, &[(1, 0xF0), (0xF, 0x20)]);
+        assert_eq!((registers[1], registers[0xF]), (0x10, 1));
+        let registers = run(0x81F5, quirks, &[(1, 0x10), (0xF, 0x20)]);
+        assert_eq!((registers[1], registers[0xF]), (0xF0, 0));
+        let registers = run(0x81F7, quirks, &[(1, 0x10), (0xF, 0x20)]);
+        assert_eq!((registers[1], registers[0xF]), (0x10, 1));
+    }
+}
+
+#[test]
+fn add_value_in_interpreter_wraps() {
+    let registers = run(0x7110, Quirks::CHIP8, &[(1, 0xF8), (0xF, 0x05)]);
+    assert_eq!((registers[1], registers[0xF]), (0x08, 0x05));
+}
+
+#[test]
+fn add_index_in_interpreter_wraps() {
+    let mut interpreter = Interpreter::with_program(&[0xF11E]);
+    interpreter.index = 0xFFFF;
+    interpreter.registers[1] = 0x02;
+    interpreter.exe().unwrap();
+    assert_eq!(interpreter.index, 0x0001);
+
+    let mut interpreter = Interpreter::with_program(&[0xF11E]);
+    interpreter.quirks.index_overflow = true;
+    interpreter.index = 0xFFFF;
+    interpreter.registers[1] = 0x02;
+    interpreter.exe().unwrap();
+    assert_eq!((interpreter.index, interpreter.registers[0xF]), (0x0001, 0));
+}
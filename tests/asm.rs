@@ -0,0 +1,204 @@
+//! The Octo assembler: what each construct assembles to and the lines its errors point at.
+
+use chip8::asm::assemble;
+
+fn assembled(source: &str) -> Vec<u16> {
+    let (bytes, _) = assemble(source, "test.8o").unwrap_or_else(|err| panic!("{err}"));
+    bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+        .collect()
+}
+
+// the line and message of the error `source` fails with
+fn error(source: &str) -> (usize, String) {
+    let err = assemble(source, "test.8o").err().expect("assembled");
+    (err.line, err.message)
+}
+
+#[test]
+fn instructions() {
+    assert_eq!(
+        assembled(
+            "clear
+            v3 := 0x2A
+            v3 += 1
+            v3 -= 1
+            v3 ^= v4
+            i := 0x300
+            i += v2
+            sprite v1 v2 5
+            delay := v5
+            v6 := key
+            return"
+        ),
+        [0x00E0, 0x632A, 0x7301, 0x73FF, 0x8343, 0xA300, 0xF21E, 0xD125, 0xF515, 0xF60A, 0x00EE]
+    );
+}
+
+#[test]
+fn error_lines() {
+    let (line, message) = error("clear\n\n  v0 := 300\nclear");
+    assert_eq!(line, 3);
+    assert_eq!(message, "300 does not fit in a byte");
+
+    let (line, message) = error("clear\n# a comment\nv0 ?= v1\n");
+    assert_eq!(line, 3);
+    assert_eq!(message, "unknown operator '?='");
+
+    // an undefined label is reported where it's used, after the whole file is read
+    let (line, message) = error("clear\njump nowhere\nclear\nclear");
+    assert_eq!(line, 2);
+    assert_eq!(message, "undefined label 'nowhere'");
+
+    let (line, message) = error(": main\nclear\n: main");
+    assert_eq!(line, 3);
+    assert_eq!(message, "label 'main' is already defined");
+}
+
+#[test]
+fn out_of_range_values() {
+    assert_eq!(
+        error("sprite v0 v1 16").1,
+        "sprite height 16 is out of range"
+    );
+    assert_eq!(error("jump 0x1000").1, "address 0x1000 is out of range");
+    assert!(error("i := -1").1.ends_with("is out of range"));
+    assert_eq!(error("v0 += -129").1, "-129 does not fit in a byte");
+    assert_eq!(error("0x100").1, "256 does not fit in a byte");
+    assert_eq!(error("v0 := v16").1, "'v16' is not a number");
+    // negative bytes are two's complement
+    assert_eq!(assembled("v0 := -1 v1 := 255"), [0x60FF, 0x61FF]);
+}
+
+#[test]
+fn consts_and_aliases() {
+    assert_eq!(
+        assembled(
+            ":const speed 3
+            :const origin 0x250
+            :alias x v4
+            :alias y va
+            x := speed
+            y += x
+            i := origin
+            sprite x y speed"
+        ),
+        [0x6403, 0x8A44, 0xA250, 0xD4A3]
+    );
+    assert_eq!(error(":alias x 4").1, "expected a register, found '4'");
+}
+
+#[test]
+fn macros() {
+    assert_eq!(
+        assembled(
+            ":macro move reg amount { reg += amount }
+            :macro twice reg { move reg 1 move reg 1 }
+            twice v2
+            move v3 0x10"
+        ),
+        [0x7201, 0x7201, 0x7310]
+    );
+
+    // errors in an expansion point at where the macro is used
+    let (line, message) = error(":macro bad { v0 := 999 }\nclear\nbad");
+    assert_eq!(line, 3);
+    assert_eq!(message, "999 does not fit in a byte");
+
+    let (line, message) = error(":macro open { clear");
+    assert_eq!(line, 1);
+    assert_eq!(message, "macro 'open' is missing its closing '}'");
+}
+
+#[test]
+fn recursive_macros_stop() {
+    let (line, message) = error(":macro forever { clear forever }\n\nforever");
+    assert_eq!(line, 3);
+    assert_eq!(message, "macro 'forever' is nested more than 64 deep");
+
+    let (line, message) = error(":macro ping { pong }\n:macro pong { ping }\nclear\nping");
+    assert_eq!(line, 4);
+    assert!(
+        message.ends_with("is nested more than 64 deep"),
+        "{message}"
+    );
+}
+
+#[test]
+fn if_blocks() {
+    assert_eq!(assembled("if v0 == 5 then clear"), [0x4005, 0x00E0]);
+    assert_eq!(
+        assembled("if v0 != v1 then clear if v2 key then clear"),
+        [0x5010, 0x00E0, 0xE2A1, 0x00E0]
+    );
+
+    // the skip is inverted to jump over the block when the condition doesn't hold
+    assert_eq!(
+        assembled("if v0 == 5 begin v1 := 1 else v1 := 2 end"),
+        [0x3005, 0x1208, 0x6101, 0x120A, 0x6102]
+    );
+    assert_eq!(
+        assembled("if v0 -key begin clear end"),
+        [0xE0A1, 0x1206, 0x00E0]
+    );
+    assert_eq!(
+        assembled("if v0 == v1 begin clear end"),
+        [0x5010, 0x1206, 0x00E0]
+    );
+
+    assert_eq!(error("clear\nelse").1, "'else' without 'begin'");
+    assert_eq!(error("end").1, "'end' without 'begin'");
+    assert_eq!(
+        error("if v0 == 1 begin\nclear").1,
+        "unterminated 'begin' or 'loop' block"
+    );
+}
+
+#[test]
+fn loops() {
+    assert_eq!(
+        assembled("loop v0 += 1 while v0 != 10 again"),
+        [0x7001, 0x400A, 0x1208, 0x1200]
+    );
+    // every `while` leaves the innermost loop
+    assert_eq!(
+        assembled(
+            "loop
+                loop while v1 == 0 v1 += -1 again
+                while v0 != 3
+            again"
+        ),
+        [0x3100, 0x1208, 0x71FF, 0x1200, 0x4003, 0x120E, 0x1200]
+    );
+    assert_eq!(error("again").1, "'again' without 'loop'");
+    assert_eq!(error("while v0 == 1").1, "'while' outside of a loop");
+}
+
+#[test]
+fn forward_labels() {
+    assert_eq!(
+        assembled(
+            ": main
+            jump start
+            : data
+            0x12 0x34
+            : start
+            i := data
+            :call draw
+            draw
+            : draw
+            i := later
+            return
+            : later"
+        ),
+        [0x1204, 0x1234, 0xA202, 0x220A, 0x220A, 0xA20E, 0x00EE]
+    );
+}
+
+#[test]
+fn symbols() {
+    let (_, symbols) = assemble(": main\n  clear\n: spin\n  jump spin", "game.8o").unwrap();
+    assert_eq!(symbols.address("spin"), Some(0x202));
+    assert_eq!(symbols.location(0x202), Some("game.8o:4".to_string()));
+}
@@ -0,0 +1,196 @@
+//! The symbol map and the console debugger's commands.
+
+use chip8::debugger::Debugger;
+use chip8::symbols::SymbolMap;
+use chip8::{Interpreter, Observer};
+
+const SYMBOLS: &str = "\
+source game.8o
+label 0x200 main
+label 0x208 draw
+label 0x210 score
+line 0x200 3
+line 0x202 4
+line 0x208 9
+";
+
+fn symbols() -> SymbolMap {
+    SymbolMap::parse(SYMBOLS).unwrap()
+}
+
+#[test]
+fn symbol_map_round_trip() {
+    let map = symbols();
+    assert_eq!(map.to_string(), SYMBOLS);
+    assert_eq!(
+        SymbolMap::parse(&map.to_string()).unwrap().to_string(),
+        SYMBOLS
+    );
+
+    // labels at one address are written in name order
+    let mut map = SymbolMap::new("a b.8o");
+    map.add_label(0x300, "b");
+    map.add_label(0x300, "a");
+    map.add_line(0x300, 7);
+    // only the first line of an address counts
+    map.add_line(0x300, 8);
+    let text = map.to_string();
+    assert_eq!(
+        text,
+        "source a b.8o\nlabel 0x300 a\nlabel 0x300 b\nline 0x300 7\n"
+    );
+    assert_eq!(SymbolMap::parse(&text).unwrap().to_string(), text);
+}
+
+#[test]
+fn symbol_map_errors() {
+    assert_eq!(
+        SymbolMap::parse("source x\nlabel 0x2g0 main")
+            .err()
+            .unwrap(),
+        "line 2: bad address '0x2g0'"
+    );
+    assert_eq!(
+        SymbolMap::parse("line 0x200 three").err().unwrap(),
+        "line 1: bad line number 'three'"
+    );
+    assert_eq!(
+        SymbolMap::parse("\nlabel main").err().unwrap(),
+        "line 2: unrecognised entry 'label main'"
+    );
+}
+
+#[test]
+fn describe_and_location() {
+    let map = symbols();
+    assert_eq!(map.address("draw"), Some(0x208));
+    assert_eq!(map.address("missing"), None);
+
+    assert_eq!(map.describe(0x200).unwrap(), "main");
+    // between two labels it's relative to the one before
+    assert_eq!(map.describe(0x206).unwrap(), "main+0x6");
+    assert_eq!(map.describe(0x208).unwrap(), "draw");
+    assert_eq!(map.describe(0x20a).unwrap(), "draw+0x2");
+    assert_eq!(map.describe(0x400).unwrap(), "score+0x1f0");
+    assert_eq!(map.describe(0x1ff), None);
+
+    assert_eq!(map.location(0x202).unwrap(), "game.8o:4");
+    assert_eq!(map.location(0x208).unwrap(), "game.8o:9");
+    // only the first address of a statement has a line
+    assert_eq!(map.location(0x203), None);
+}
+
+fn debugger() -> Debugger {
+    let mut debugger = Debugger::new();
+    debugger.symbols = symbols();
+    debugger
+}
+
+#[test]
+fn breakpoints_by_label_and_address() {
+    let mut debugger = debugger();
+    let mut interpreter = Interpreter::new();
+    debugger.command("break draw", &mut interpreter).unwrap();
+    debugger.command("b 0x20c", &mut interpreter).unwrap();
+    debugger.command("b 2a0", &mut interpreter).unwrap();
+    assert_eq!(
+        debugger.breakpoints().collect::<Vec<_>>(),
+        [0x208, 0x20c, 0x2a0]
+    );
+
+    debugger.command("delete 0x208", &mut interpreter).unwrap();
+    debugger.command("d 2a0", &mut interpreter).unwrap();
+    assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), [0x20c]);
+
+    assert_eq!(
+        debugger.command("break nowhere", &mut interpreter),
+        Err("unknown label or address 'nowhere'".to_string())
+    );
+    assert_eq!(
+        debugger.command("delete draw", &mut interpreter),
+        Err("no breakpoint at 0x208 draw (game.8o:9)".to_string())
+    );
+    assert_eq!(
+        debugger.command("break", &mut interpreter),
+        Err("unknown command 'break', type 'help' for commands".to_string())
+    );
+}
+
+#[test]
+fn stops_at_breakpoints() {
+    let mut debugger = debugger();
+    // main calls draw, which loops
+    let mut interpreter = Interpreter::with_program(&[0x2208, 0x1200, 0, 0, 0x6001, 0x120A]);
+    debugger.command("break 0x20a", &mut interpreter).unwrap();
+
+    let run = |debugger: &mut Debugger, interpreter: &mut Interpreter| {
+        for _ in 0..10 {
+            if debugger.before_exe(interpreter) {
+                return;
+            }
+            interpreter.exe().unwrap();
+        }
+    };
+    run(&mut debugger, &mut interpreter);
+    assert!(debugger.is_paused());
+    assert_eq!(interpreter.program_counter, 0x20a);
+
+    // continuing runs past the breakpoint it stopped at, once
+    debugger.command("continue", &mut interpreter).unwrap();
+    assert!(!debugger.before_exe(&interpreter));
+    interpreter.exe().unwrap();
+    assert!(debugger.before_exe(&interpreter));
+
+    debugger.command("step 2", &mut interpreter).unwrap();
+    assert_eq!(interpreter.program_counter, 0x20a);
+    assert!(debugger.is_paused());
+    assert_eq!(
+        debugger.command("step two", &mut interpreter),
+        Err("'two' is not a number".to_string())
+    );
+}
+
+#[test]
+fn backtrace_names_frames() {
+    let mut debugger = debugger();
+    let mut interpreter = Interpreter::new();
+    interpreter.program_counter = 0x20a;
+    // draw was called from main+0x2, which was called from 0x204
+    interpreter.stack = vec![0x206, 0x204];
+    assert_eq!(
+        debugger.backtrace(&interpreter),
+        [
+            "#0 0x20a draw+0x2",
+            "#1 0x202 main+0x2 (game.8o:4)",
+            "#2 0x204 main+0x4",
+        ]
+    );
+
+    debugger.symbols = SymbolMap::default();
+    interpreter.stack.clear();
+    assert_eq!(debugger.backtrace(&interpreter), ["#0 0x20a"]);
+    debugger.command("bt", &mut interpreter).unwrap();
+}
+
+#[test]
+fn poke() {
+    let mut debugger = debugger();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        debugger.command("poke score 1", &mut interpreter),
+        Err("pause before writing to memory".to_string())
+    );
+    debugger.pause();
+    debugger
+        .command("poke score 12 0x34", &mut interpreter)
+        .unwrap();
+    assert_eq!(interpreter.memory[0x210..0x212], [0x12, 0x34]);
+    assert_eq!(
+        debugger.command("poke 0xfff 1 2", &mut interpreter),
+        Err("write goes past the end of memory".to_string())
+    );
+    assert_eq!(
+        debugger.command("poke ffffffffffffffff 1 2", &mut interpreter),
+        Err("write goes past the end of memory".to_string())
+    );
+}
@@ -0,0 +1,52 @@
+//! Random programs run on the interpreter and on the reference model in `reference`, which
+//! must agree after every instruction, and random ROMs, which must never make `exe` panic.
+//! The fuzz targets in `fuzz/` do the same with inputs from libFuzzer.
+
+mod reference;
+
+use chip8::{Interpreter, Quirks, OFFSET};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const PROGRAMS: u64 = 2000;
+const STEPS: usize = 200;
+
+#[test]
+fn random_programs_match_reference() {
+    for seed in 0..PROGRAMS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, quirks) = Quirks::PRESETS[rng.gen_range(0..Quirks::PRESETS.len())];
+        let quirks = Quirks {
+            index_overflow: rng.gen(),
+            ..quirks
+        };
+        let program: Vec<u8> = (0..rng.gen_range(2..256)).map(|_| rng.gen()).collect();
+        // mostly small values, so that memory accesses through I often succeed
+        let index = match rng.gen_bool(0.8) {
+            true => rng.gen_range(0..0x1000),
+            false => rng.gen(),
+        };
+
+        if let Err(diff) = reference::compare(&program, quirks, rng.gen(), index, STEPS) {
+            panic!("seed {seed}, {quirks:?}, program {program:02x?}\n{diff}");
+        }
+    }
+}
+
+#[test]
+fn random_roms_never_panic() {
+    for seed in 0..PROGRAMS {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let rom: Vec<u8> = (0..rng.gen_range(0..=4096 - OFFSET))
+            .map(|_| rng.gen())
+            .collect();
+        let mut interpreter = Interpreter::new();
+        interpreter.quirks = Quirks::PRESETS[seed as usize % Quirks::PRESETS.len()].1;
+        interpreter.load_rom(&rom);
+        for _ in 0..STEPS * 10 {
+            if interpreter.exe().is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! The disassembler: which opcodes it decodes, how the trace tells code from data, the labels
+//! it makes up and the listing, which has to assemble back into the ROM.
+
+use std::path::Path;
+
+use chip8::disasm::{disassemble, flow, Analysis, Flow};
+use chip8::{asm, ExeError, Interpreter};
+
+fn rom(opcodes: &[u16]) -> Vec<u8> {
+    opcodes.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+#[test]
+fn decodes_what_the_interpreter_runs() {
+    for opcode in 0..=0xFFFF {
+        let mut interpreter = Interpreter::with_program(&[opcode]);
+        // somewhere to return to
+        interpreter.stack.push(0x200);
+        let unknown = matches!(interpreter.exe(), Err(ExeError::UnknownOpcode { .. }));
+        assert_eq!(
+            disassemble(opcode).is_some(),
+            !unknown,
+            "{opcode:04x}: {:?}",
+            disassemble(opcode)
+        );
+        assert_eq!(flow(opcode) == Flow::Invalid, unknown, "{opcode:04x}");
+    }
+}
+
+#[test]
+fn register_skips_with_any_low_digit() {
+    assert_eq!(disassemble(0x5120).unwrap(), "if v1 != v2 then");
+    assert_eq!(disassemble(0x5121).unwrap(), "if v1 != v2 then");
+    assert_eq!(disassemble(0x934F).unwrap(), "if v3 == v4 then");
+    assert_eq!(flow(0x5127), Flow::Skip);
+    assert_eq!(flow(0x9341), Flow::Skip);
+}
+
+#[test]
+fn flows() {
+    assert_eq!(flow(0x00EE), Flow::Return);
+    assert_eq!(flow(0x1234), Flow::Jump(0x234));
+    assert_eq!(flow(0x2456), Flow::Call(0x456));
+    assert_eq!(flow(0xB300), Flow::ComputedJump(0x300));
+    assert_eq!(flow(0xE19E), Flow::Skip);
+    assert_eq!(flow(0x6105), Flow::Next);
+    assert_eq!(flow(0x0123), Flow::Invalid);
+    assert_eq!(flow(0x8008), Flow::Invalid);
+}
+
+// a subroutine, data behind a jump and data pointed at by I
+const PROGRAM: [u16; 7] = [
+    0x2208, // :call sub_208
+    0xA20C, // i := data_20c
+    0x1204, // jump label_204
+    0x1234, // never reached
+    0x5121, // if v1 != v2 then
+    0x00EE, // return
+    0xF090, // sprite data
+];
+
+#[test]
+fn code_and_data() {
+    let analysis = Analysis::trace(&rom(&PROGRAM));
+    let listing = analysis.listing();
+    assert_eq!(
+        listing,
+        "\
+: main
+\t:call sub_208            # 200: 2208
+\ti := data_20c            # 202: a20c
+: label_204
+\tjump label_204           # 204: 1204
+\t0x12 0x34
+: sub_208
+\t0x51 0x21                # 208: 5121 if v1 != v2 then
+\treturn                   # 20a: 00ee
+: data_20c
+\t0xF0 0x90
+"
+    );
+}
+
+#[test]
+fn computed_jumps_are_noted() {
+    let analysis = Analysis::trace(&rom(&[0xB206, 0x1202, 0x1202, 0x00E0]));
+    assert_eq!(analysis.computed_jumps().collect::<Vec<_>>(), [0x200]);
+    let listing = analysis.listing();
+    assert!(listing.contains("jump0 data_206           # 200: b206 computed jump"));
+    // the targets aren't traced, so they stay data
+    assert!(listing.contains(": data_206\n\t0x00 0xE0\n"));
+}
+
+#[test]
+fn labels_only_inside_the_rom() {
+    let analysis = Analysis::trace(&rom(&[0x2400, 0xA500, 0x1200]));
+    let listing = analysis.listing();
+    assert!(listing.contains(":call 0x400"));
+    assert!(listing.contains("i := 0x500"));
+    assert!(!listing.contains("sub_400"));
+}
+
+#[test]
+fn jump_into_an_instruction() {
+    // the jump lands on the second byte of 6112, which can't be one instruction any more
+    let analysis = Analysis::trace(&rom(&[0x6112, 0x1201]));
+    let listing = analysis.listing();
+    assert!(listing.contains("\t0x61\n: label_201\n"));
+    let (bytes, _) = asm::assemble(&listing, "listing.8o").unwrap();
+    assert_eq!(bytes, rom(&[0x6112, 0x1201]));
+}
+
+#[test]
+fn listing_assembles_back() {
+    let mut roms = vec![rom(&PROGRAM)];
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "8o") {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let (bytes, _) = asm::assemble(&source, &path.display().to_string()).unwrap();
+            roms.push(bytes);
+        }
+    }
+    assert!(roms.len() > 1, "no programs in tests/roms");
+
+    for rom in roms {
+        let listing = Analysis::trace(&rom).listing();
+        let (bytes, _) =
+            asm::assemble(&listing, "listing.8o").unwrap_or_else(|err| panic!("{err}\n{listing}"));
+        assert_eq!(bytes, rom, "\n{listing}");
+    }
+}
@@ -0,0 +1,144 @@
+//! Software filters on the scaled up screen.
+
+use chip8::config::Config;
+use chip8::filter::render;
+use chip8::{screen, Filter, Interpreter, HEIGHT, WIDTH};
+
+const WHITE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const BLACK: [u8; 4] = [0, 0, 0, 0xff];
+
+// a frame of `width` x `height` pixels, black where `lit` says
+fn frame(width: usize, height: usize, lit: &[(usize, usize)]) -> Vec<u8> {
+    let mut frame = WHITE.repeat(width * height);
+    for &(x, y) in lit {
+        frame[(y * width + x) * 4..][..4].copy_from_slice(&BLACK);
+    }
+    frame
+}
+
+fn pixel(frame: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+    frame[(y * width + x) * 4..][..4].try_into().unwrap()
+}
+
+fn filtered(from: &[u8], size: (usize, usize), scale: usize, filters: &[Filter]) -> Vec<u8> {
+    let to = (size.0 * scale, size.1 * scale);
+    let mut out = vec![0; to.0 * to.1 * 4];
+    render(from, size, &mut out, to, filters);
+    out
+}
+
+#[test]
+fn parse_list() {
+    assert_eq!(Filter::parse_list("none"), Some(vec![]));
+    assert_eq!(
+        Filter::parse_list("scanlines, grid,rounded"),
+        Some(vec![Filter::Scanlines, Filter::Grid, Filter::Rounded])
+    );
+    assert_eq!(
+        Filter::parse_list("scale2x,scale2x"),
+        Some(vec![Filter::Scale2x, Filter::Scale2x])
+    );
+    assert_eq!(Filter::parse_list("hq2x"), None);
+    assert_eq!(Filter::parse_list(""), None);
+}
+
+#[test]
+fn no_filters_is_nearest_neighbour() {
+    let from = frame(2, 2, &[(1, 0)]);
+    assert_eq!(
+        filtered(&from, (2, 2), 3, &[]),
+        screen::scale_rgba(&from, 2, 3)
+    );
+}
+
+#[test]
+fn scanlines_darken_the_bottom_half() {
+    let out = filtered(&frame(1, 1, &[]), (1, 1), 4, &[Filter::Scanlines]);
+    for y in 0..4 {
+        let [r, g, b, a] = pixel(&out, 4, 0, y);
+        assert_eq!(r == 0xff, y < 2, "row {y}");
+        assert!(r == g && g == b && a == 0xff);
+    }
+    // nothing to darken at 1:1
+    let out = filtered(&frame(2, 2, &[]), (2, 2), 1, &[Filter::Scanlines]);
+    assert_eq!(out, frame(2, 2, &[]));
+}
+
+#[test]
+fn grid_darkens_the_edges() {
+    let out = filtered(&frame(2, 1, &[]), (2, 1), 3, &[Filter::Grid]);
+    for (x, y) in [(0, 0), (1, 0), (3, 1), (4, 0)] {
+        let edge = x % 3 == 0 || y == 0;
+        assert_eq!(pixel(&out, 6, x, y) == WHITE, !edge, "{x},{y}");
+    }
+}
+
+#[test]
+fn rounded_cuts_lone_corners() {
+    // a lone black pixel in the middle of white
+    let from = frame(3, 3, &[(1, 1)]);
+    let out = filtered(&from, (3, 3), 4, &[Filter::Rounded]);
+    // its corners go white, its middle and edges stay black
+    assert_eq!(pixel(&out, 12, 4, 4), WHITE);
+    assert_eq!(pixel(&out, 12, 7, 7), WHITE);
+    assert_eq!(pixel(&out, 12, 5, 5), BLACK);
+    assert_eq!(pixel(&out, 12, 5, 4), BLACK);
+
+    // pixels in a solid line keep their corners
+    let from = frame(3, 1, &[(0, 0), (1, 0), (2, 0)]);
+    let out = filtered(&from, (3, 1), 4, &[Filter::Rounded]);
+    assert!(out.chunks_exact(4).all(|pixel| pixel == BLACK));
+}
+
+#[test]
+fn scale2x_smooths_diagonals() {
+    // a diagonal from the top left
+    let from = frame(3, 3, &[(0, 0), (1, 1), (2, 2)]);
+    let out = filtered(&from, (3, 3), 2, &[Filter::Scale2x]);
+    // the steps between the black pixels are filled in
+    assert_eq!(pixel(&out, 6, 2, 1), BLACK);
+    assert_eq!(pixel(&out, 6, 1, 2), BLACK);
+    assert_eq!(pixel(&out, 6, 4, 3), BLACK);
+    assert_eq!(pixel(&out, 6, 3, 0), WHITE);
+    assert_eq!(pixel(&out, 6, 0, 3), WHITE);
+    assert_eq!(pixel(&filtered(&from, (3, 3), 2, &[]), 6, 2, 1), WHITE);
+
+    // only as often as the scale has room for
+    assert_eq!(
+        filtered(&from, (3, 3), 2, &[Filter::Scale2x, Filter::Scale2x]),
+        out
+    );
+    // a square stays a square
+    let from = frame(2, 2, &[(0, 0), (1, 0), (0, 1), (1, 1)]);
+    let out = filtered(&from, (2, 2), 4, &[Filter::Scale2x, Filter::Scale2x]);
+    assert!(out.chunks_exact(4).all(|pixel| pixel == BLACK));
+}
+
+#[test]
+fn screenshots_are_filtered() {
+    let mut interpreter = Interpreter::new();
+    interpreter.screen[0][0] = true;
+    let plain = screen::render(&interpreter, 2);
+    interpreter.filters = vec![Filter::Scanlines];
+    let scanlines = screen::render(&interpreter, 2);
+    assert_eq!(scanlines.len(), WIDTH * HEIGHT * 16);
+    assert_eq!(scanlines[..WIDTH * 8], plain[..WIDTH * 8]);
+    assert_ne!(
+        scanlines[WIDTH * 8..WIDTH * 16],
+        plain[WIDTH * 8..WIDTH * 16]
+    );
+}
+
+#[test]
+fn config() {
+    let config = Config::parse("filter = scanlines\n[pong.ch8]\nfilter = none\n").unwrap();
+    assert_eq!(
+        config.filters_for("tetris.ch8"),
+        Some(vec![Filter::Scanlines])
+    );
+    assert_eq!(config.filters_for("roms/pong.ch8"), Some(vec![]));
+    assert_eq!(
+        Config::parse("filter = blur").err().unwrap(),
+        "line 1: bad filter 'blur'"
+    );
+}
@@ -0,0 +1,245 @@
+//! The GDB stub over a real socket: raw packets in, acknowledgements and replies out.
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chip8::debugger::Debugger;
+use chip8::gdb::GdbStub;
+use chip8::{Interpreter, Observer};
+
+struct Session {
+    stub: GdbStub,
+    client: TcpStream,
+    interpreter: Interpreter,
+    debugger: Debugger,
+}
+
+impl Session {
+    fn new(program: &[u16]) -> Self {
+        let mut stub = GdbStub::bind(0).unwrap();
+        let client = TcpStream::connect(("127.0.0.1", stub.port())).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let mut interpreter = Interpreter::with_program(program);
+        let mut debugger = Debugger::new();
+        stub.poll(&mut interpreter, &mut debugger);
+        assert!(debugger.is_paused(), "stopped once GDB attaches");
+        Self {
+            stub,
+            client,
+            interpreter,
+            debugger,
+        }
+    }
+
+    // send raw bytes and return everything the stub writes back
+    fn raw(&mut self, bytes: &[u8]) -> String {
+        self.client.write_all(bytes).unwrap();
+        self.collect()
+    }
+
+    fn collect(&mut self) -> String {
+        let mut out = Vec::new();
+        let mut buffer = [0; 4096];
+        for _ in 0..5 {
+            self.stub.poll(&mut self.interpreter, &mut self.debugger);
+            match self.client.read(&mut buffer) {
+                Ok(n) => out.extend_from_slice(&buffer[..n]),
+                // nothing more once something other than an acknowledgement came
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if out.last().is_some_and(|&b| b != b'+') {
+                        break;
+                    }
+                }
+                Err(err) => panic!("{err}"),
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    // send a well-formed packet and return the data of its reply
+    fn packet(&mut self, data: &str) -> String {
+        let out = self.raw(frame(data).as_bytes());
+        let reply = out
+            .strip_prefix('+')
+            .unwrap_or_else(|| panic!("no ack: {out:?}"));
+        unframe(reply)
+    }
+}
+
+fn frame(data: &str) -> String {
+    let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+    format!("${data}#{checksum:02x}")
+}
+
+// the data of one reply, checking its checksum
+fn unframe(reply: &str) -> String {
+    let data = reply
+        .strip_prefix('$')
+        .and_then(|rest| rest.rsplit_once('#'))
+        .unwrap_or_else(|| panic!("not a packet: {reply:?}"));
+    assert_eq!(frame(data.0), reply);
+    data.0.to_string()
+}
+
+#[test]
+fn framing() {
+    let mut session = Session::new(&[0x1200]);
+    assert_eq!(session.packet("?"), "S05");
+
+    // a bad checksum is refused and not answered
+    assert_eq!(session.raw(b"$?#00"), "-");
+
+    // acknowledgements are skipped, and two packets in one read both get replies
+    let out = session.raw(format!("+{}{}", frame("qC"), frame("qAttached")).as_bytes());
+    assert_eq!(out, format!("+{}+{}", frame("QC1"), frame("1")));
+
+    // a packet split across reads is answered once it's complete
+    let packet = frame("qAttached");
+    let (start, checksum) = packet.split_at(packet.len() - 2);
+    assert_eq!(session.raw(start.as_bytes()), "");
+    assert_eq!(session.raw(&checksum.as_bytes()[..1]), "");
+    assert_eq!(
+        session.raw(&checksum.as_bytes()[1..]),
+        format!("+{}", frame("1"))
+    );
+
+    // Ctrl-C stops without a packet or an acknowledgement
+    session.debugger.resume(&session.interpreter);
+    assert_eq!(session.raw(&[0x03]), frame("S02"));
+    assert!(session.debugger.is_paused());
+
+    // unknown packets get the empty reply
+    assert_eq!(session.packet("vMustReplyEmpty"), "");
+}
+
+#[test]
+fn registers() {
+    let mut session = Session::new(&[0x1200]);
+    session.interpreter.registers[0] = 0x12;
+    session.interpreter.registers[0xF] = 0xAB;
+    session.interpreter.index = 0x345;
+    session.interpreter.stack = vec![0x204, 0x206];
+    session.interpreter.delay_timer = 7;
+    session.interpreter.sound_timer = 9;
+    assert_eq!(
+        session.packet("g"),
+        // v0-vf, then I and PC little endian, SP, DT, ST
+        format!("12{}ab 4503 0002 02 07 09", "00".repeat(14)).replace(' ', "")
+    );
+
+    let registers = "0102030405060708090a0b0c0d0e0f10" // v0-vf
+        .to_string()
+        + "2103" // I = 0x321
+        + "0a02" // PC = 0x20a
+        + "01" // SP
+        + "0506"; // DT, ST
+    assert_eq!(session.packet(&format!("G{registers}")), "OK");
+    assert_eq!(session.packet("g"), registers);
+    assert_eq!(session.interpreter.registers[0xA], 0x0b);
+    assert_eq!(session.interpreter.index, 0x321);
+    assert_eq!(session.interpreter.program_counter, 0x20a);
+    assert_eq!(session.interpreter.stack, [0x204]);
+    assert_eq!(
+        (
+            session.interpreter.delay_timer,
+            session.interpreter.sound_timer
+        ),
+        (5, 6)
+    );
+
+    // one register short
+    assert_eq!(session.packet(&format!("G{}", &registers[..40])), "E01");
+    assert_eq!(session.packet("G01zz"), "E01");
+
+    assert_eq!(session.packet("p10"), "2103");
+    assert_eq!(session.packet("P3=ff"), "OK");
+    assert_eq!(session.interpreter.registers[3], 0xff);
+    assert_eq!(session.packet("p15"), "E01");
+    assert_eq!(session.packet("P15=00"), "E01");
+}
+
+#[test]
+fn memory() {
+    let mut session = Session::new(&[0x1234, 0x5678]);
+    assert_eq!(session.packet("m200,4"), "12345678");
+    assert_eq!(session.packet("mffe,2"), "0000");
+    assert_eq!(session.packet("mfff,2"), "E01");
+    assert_eq!(session.packet("m200"), "E01");
+
+    assert_eq!(session.packet("M300,3:a1b2c3"), "OK");
+    assert_eq!(session.interpreter.memory[0x300..0x303], [0xa1, 0xb2, 0xc3]);
+    // the length doesn't match the data
+    assert_eq!(session.packet("M300,2:a1b2c3"), "E01");
+    assert_eq!(session.packet("Mfff,2:0102"), "E01");
+    assert_eq!(session.packet("M300,1:zz"), "E01");
+
+    // ranges that overflow are refused, not a panic
+    assert_eq!(session.packet("mffffffffffffffff,2"), "E01");
+    assert_eq!(session.packet("m1,ffffffffffffffff"), "E01");
+    assert_eq!(session.packet("Mffffffffffffffff,1:00"), "E01");
+}
+
+#[test]
+fn target_description() {
+    let mut session = Session::new(&[0x1200]);
+    assert_eq!(
+        session.packet("qSupported:multiprocess+"),
+        "PacketSize=1000;qXfer:features:read+"
+    );
+    let first = session.packet("qXfer:features:read:target.xml:0,15");
+    assert_eq!(first, "m<?xml version=\"1.0\"?>");
+    let last = session.packet("qXfer:features:read:target.xml:15,1000");
+    assert!(last.starts_with("l\n<!DOCTYPE"), "{last}");
+    assert!(last.ends_with("</target>\n"), "{last}");
+    assert_eq!(
+        session.packet("qXfer:features:read:target.xml:2000,10"),
+        "l"
+    );
+    assert_eq!(
+        session.packet("qXfer:features:read:target.xml:10,ffffffffffffffff"),
+        "E01"
+    );
+}
+
+#[test]
+fn breakpoints_and_continue() {
+    // 0x202 loops back to 0x200
+    let mut session = Session::new(&[0x6001, 0x1200]);
+    assert_eq!(session.packet("Z0,202,2"), "OK");
+    assert_eq!(session.packet("Z1,204,2"), "OK");
+    assert_eq!(
+        session.debugger.breakpoints().collect::<Vec<_>>(),
+        [0x202, 0x204]
+    );
+    assert_eq!(session.packet("z1,204,2"), "OK");
+    assert_eq!(session.debugger.breakpoints().collect::<Vec<_>>(), [0x202]);
+    // watchpoints aren't supported
+    assert_eq!(session.packet("Z2,300,1"), "");
+
+    // `c` is answered when the program stops
+    assert_eq!(session.raw(frame("c").as_bytes()), "+");
+    assert!(!session.debugger.is_paused());
+    while !session.debugger.before_exe(&session.interpreter) {
+        session.interpreter.exe().unwrap();
+    }
+    assert_eq!(session.collect(), frame("S05"));
+    assert_eq!(session.interpreter.program_counter, 0x202);
+}
+
+#[test]
+fn step() {
+    let mut session = Session::new(&[0x6001, 0x7001, 0x0123]);
+    assert_eq!(session.packet("s"), "S05");
+    assert_eq!(session.interpreter.registers[0], 1);
+    assert_eq!(session.packet("s"), "S05");
+    assert_eq!(session.interpreter.registers[0], 2);
+    // an instruction that faults
+    assert_eq!(session.packet("s"), "S0b");
+    // from another address
+    assert_eq!(session.packet("s202"), "S05");
+    assert_eq!(session.interpreter.registers[0], 3);
+    assert_eq!(session.interpreter.program_counter, 0x204);
+}
@@ -0,0 +1,164 @@
+//! Runs test ROMs without a window under each quirk preset and compares the final screen
+//! against the images in `tests/golden`. Set `UPDATE_GOLDEN=1` to write the images of the
+//! programs in `tests/roms` instead, which are assembled and always run.
+//!
+//! The standard test suite (https://github.com/Timendus/chip8-test-suite) is read from `roms/`,
+//! which isn't part of the repository, so its test is ignored by default. Copy the suite's
+//! `bin/*.ch8` into `roms/` and run it with `cargo test --test golden -- --ignored`, where a
+//! missing ROM fails. Its images are the screens the suite documents as passing, checked by
+//! hand, and never written by `UPDATE_GOLDEN`: that would only record what the interpreter
+//! draws today.
+
+use std::path::Path;
+
+use chip8::headless::{self, KeyPress, StopCondition};
+use chip8::{asm, screen, Interpreter, Quirks};
+
+// ROM in `roms/`, quirk preset, frames to run and scripted keys as `frame:key[:frames]`
+const SUITE: &[(&str, &str, u64, &[&str])] = &[
+    ("1-chip8-logo.ch8", "chip8", 60, &[]),
+    ("1-chip8-logo.ch8", "superchip", 60, &[]),
+    ("1-chip8-logo.ch8", "xochip", 60, &[]),
+    ("2-ibm-logo.ch8", "chip8", 60, &[]),
+    ("2-ibm-logo.ch8", "superchip", 60, &[]),
+    ("2-ibm-logo.ch8", "xochip", 60, &[]),
+    ("3-corax+.ch8", "chip8", 60, &[]),
+    ("3-corax+.ch8", "superchip", 60, &[]),
+    ("3-corax+.ch8", "xochip", 60, &[]),
+    ("4-flags.ch8", "chip8", 60, &[]),
+    ("4-flags.ch8", "superchip", 60, &[]),
+    ("4-flags.ch8", "xochip", 60, &[]),
+    // the platform is picked from a menu, SUPER-CHIP then asks for modern or legacy
+    ("5-quirks.ch8", "chip8", 300, &["30:1:2"]),
+    ("5-quirks.ch8", "superchip", 300, &["30:2:2", "60:1:2"]),
+    ("5-quirks.ch8", "xochip", 300, &["30:3:2"]),
+    // the FX0A test, then a press of key 5
+    ("6-keypad.ch8", "chip8", 120, &["30:3:2", "60:5:2"]),
+    ("6-keypad.ch8", "superchip", 120, &["30:3:2", "60:5:2"]),
+    ("6-keypad.ch8", "xochip", 120, &["30:3:2", "60:5:2"]),
+];
+
+// programs in `tests/roms`, run under every preset until they halt
+const PROGRAMS: &[&str] = &["quirks", "sprites"];
+
+fn run(rom: &[u8], preset: &str, frames: u64, keys: &[&str], halt: bool) -> String {
+    run_with(
+        rom,
+        Quirks::preset(preset).unwrap(),
+        preset,
+        frames,
+        keys,
+        halt,
+    )
+}
+
+fn run_with(
+    rom: &[u8],
+    quirks: Quirks,
+    preset: &str,
+    frames: u64,
+    keys: &[&str],
+    halt: bool,
+) -> String {
+    let mut interpreter = Interpreter::new();
+    interpreter.quirks = quirks;
+    interpreter.load_rom(rom);
+
+    let presses: Vec<KeyPress> = keys.iter().map(|k| KeyPress::parse(k).unwrap()).collect();
+    let mut stop = StopCondition {
+        halt,
+        ..Default::default()
+    };
+    let (frame, result) = headless::run(&mut interpreter, frames, &presses, &mut stop);
+    if let Err(err) = result {
+        panic!("{preset}: frame {frame}: {err}");
+    }
+    assert!(
+        !halt || stop.reached,
+        "{preset}: did not halt in {frames} frames"
+    );
+    screen::to_text(&interpreter.screen)
+}
+
+// compare with the golden image, returning why they differ, or write it with `UPDATE_GOLDEN`
+// when `update` allows
+fn check(name: &str, actual: &str, update: bool) -> Option<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.txt"));
+    if update && std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return None;
+    }
+    match std::fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => None,
+        Ok(expected) => Some(format!("{name}: expected\n{expected}got\n{actual}")),
+        Err(_) if update => Some(format!(
+            "{name}: no image at {}, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )),
+        Err(_) => Some(format!(
+            "{name}: no image at {}, add the screen the suite shows when it passes",
+            path.display()
+        )),
+    }
+}
+
+fn assert_all(failures: Vec<String>) {
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite in roms/"]
+fn test_suite() {
+    let roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+    let mut failures = vec![];
+    for &(file, preset, frames, keys) in SUITE {
+        let Ok(rom) = std::fs::read(roms.join(file)) else {
+            failures.push(format!("{file}: not in {}", roms.display()));
+            continue;
+        };
+        let actual = run(&rom, preset, frames, keys, false);
+        let stem = file.trim_end_matches(".ch8");
+        failures.extend(check(&format!("{stem}-{preset}"), &actual, false));
+    }
+    assert_all(failures);
+}
+
+#[test]
+fn programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    let mut failures = vec![];
+    for name in PROGRAMS {
+        let file = format!("{name}.8o");
+        let rom = assemble(&dir, &file);
+        for (preset, _) in Quirks::PRESETS {
+            let actual = run(&rom, preset, 60, &[], true);
+            failures.extend(check(&format!("{name}-{preset}"), &actual, true));
+        }
+    }
+    assert_all(failures);
+}
+
+// the display wait quirk is off in every preset, so it has an image of its own
+#[test]
+fn display_wait() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    let rom = assemble(&dir, "display-wait.8o");
+    let quirks = Quirks {
+        display_wait: true,
+        ..Quirks::CHIP8
+    };
+    let actual = run_with(&rom, quirks, "chip8 with display wait", 60, &[], true);
+    assert_all(
+        check("display-wait-chip8", &actual, true)
+            .into_iter()
+            .collect(),
+    );
+}
+
+fn assemble(dir: &Path, file: &str) -> Vec<u8> {
+    let source = std::fs::read_to_string(dir.join(file)).unwrap();
+    let (rom, _) = asm::assemble(&source, file).unwrap_or_else(|err| panic!("{file}: {err}"));
+    rom
+}
@@ -0,0 +1,30 @@
+//! The debugger's hex dump of memory.
+
+use chip8::hexview::MemoryView;
+use chip8::Interpreter;
+
+#[test]
+fn dump_rows() {
+    let mut interpreter = Interpreter::with_program(&[0x1234]);
+    interpreter.index = 0xF00;
+    let dump = MemoryView::new().dump(&interpreter, 0x1fe, 4);
+    // the PC in yellow, padded to whole rows
+    assert_eq!(
+        dump,
+        format!(
+            "1f0:{} 00 00\n200: \x1b[33m12\x1b[0m \x1b[33m34\x1b[0m{}\n",
+            "   ".repeat(14),
+            "   ".repeat(14)
+        )
+    );
+}
+
+#[test]
+fn dump_stops_at_the_end_of_memory() {
+    let interpreter = Interpreter::new();
+    let view = MemoryView::new();
+    let dump = view.dump(&interpreter, 0xff0, usize::MAX);
+    assert_eq!(dump, format!("ff0:{}\n", " 00".repeat(16)));
+    assert_eq!(view.dump(&interpreter, usize::MAX, usize::MAX), "");
+    assert_eq!(view.dump(&interpreter, 0x1000, 0x10), "");
+}
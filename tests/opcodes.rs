@@ -0,0 +1,618 @@
+//! One or more tests for every instruction `Interpreter::exe` knows, set up through
+//! `Interpreter::with_program` and the machine's fields.
+
+use chip8::{ExeError, Interpreter, KeypadKey, Quirks, HEIGHT, OFFSET, STACK_SIZE, WIDTH};
+
+fn step(interpreter: &mut Interpreter, count: usize) {
+    for _ in 0..count {
+        interpreter.exe().unwrap();
+    }
+}
+
+fn run(opcode: u16, setup: impl FnOnce(&mut Interpreter)) -> Interpreter {
+    let mut interpreter = Interpreter::with_program(&[opcode]);
+    setup(&mut interpreter);
+    step(&mut interpreter, 1);
+    interpreter
+}
+
+fn lit_pixels(interpreter: &Interpreter) -> usize {
+    interpreter
+        .screen
+        .iter()
+        .flatten()
+        .filter(|&&on| on)
+        .count()
+}
+
+#[test]
+fn clear_screen() {
+    let interpreter = run(0x00E0, |m| {
+        m.screen[3][5] = true;
+        m.screen[HEIGHT - 1][WIDTH - 1] = true;
+    });
+    assert_eq!(lit_pixels(&interpreter), 0);
+    assert_eq!(interpreter.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn call_and_return() {
+    let mut interpreter = Interpreter::with_program(&[0x2206, 0x0000, 0x0000, 0x00EE]);
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, 0x206);
+    assert_eq!(interpreter.stack, vec![0x202]);
+
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, 0x202);
+    assert!(interpreter.stack.is_empty());
+}
+
+#[test]
+fn return_with_empty_stack() {
+    let mut interpreter = Interpreter::with_program(&[0x00EE]);
+    assert_eq!(
+        interpreter.exe(),
+        Err(ExeError::StackUnderflow { addr: OFFSET })
+    );
+}
+
+#[test]
+fn call_with_full_stack() {
+    // calls itself until the stack is full
+    let mut interpreter = Interpreter::with_program(&[0x2200]);
+    step(&mut interpreter, STACK_SIZE);
+    assert_eq!(interpreter.stack, vec![0x202; STACK_SIZE]);
+    assert_eq!(
+        interpreter.exe(),
+        Err(ExeError::StackOverflow { addr: OFFSET })
+    );
+    assert_eq!(interpreter.stack.len(), STACK_SIZE);
+}
+
+#[test]
+fn unknown_opcodes() {
+    for opcode in [0x0123, 0x8128, 0x810F, 0xE1FF, 0xF1FF] {
+        let mut interpreter = Interpreter::with_program(&[opcode]);
+        assert_eq!(
+            interpreter.exe(),
+            Err(ExeError::UnknownOpcode {
+                addr: OFFSET,
+                opcode
+            }),
+            "{opcode:04x}"
+        );
+    }
+}
+
+#[test]
+fn opcode_past_end_of_memory() {
+    let mut interpreter = Interpreter::with_program(&[]);
+    interpreter.program_counter = 0xFFF;
+    assert_eq!(
+        interpreter.exe(),
+        Err(ExeError::MemoryOutOfBounds {
+            addr: 0xFFF,
+            access: 0x1000
+        })
+    );
+}
+
+#[test]
+fn jump() {
+    let interpreter = run(0x1234, |_| ());
+    assert_eq!(interpreter.program_counter, 0x234);
+}
+
+#[test]
+fn skip_if_equal_to_value() {
+    let skipped = run(0x3142, |m| m.registers[1] = 0x42);
+    assert_eq!(skipped.program_counter, OFFSET + 4);
+    let not_skipped = run(0x3143, |m| m.registers[1] = 0x42);
+    assert_eq!(not_skipped.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn skip_if_not_equal_to_value() {
+    let skipped = run(0x4143, |m| m.registers[1] = 0x42);
+    assert_eq!(skipped.program_counter, OFFSET + 4);
+    let not_skipped = run(0x4142, |m| m.registers[1] = 0x42);
+    assert_eq!(not_skipped.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn skip_if_registers_equal() {
+    let skipped = run(0x5120, |m| m.registers[1..3].copy_from_slice(&[7, 7]));
+    assert_eq!(skipped.program_counter, OFFSET + 4);
+    let not_skipped = run(0x5120, |m| m.registers[1..3].copy_from_slice(&[7, 8]));
+    assert_eq!(not_skipped.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn skip_if_registers_not_equal() {
+    let skipped = run(0x9120, |m| m.registers[1..3].copy_from_slice(&[7, 8]));
+    assert_eq!(skipped.program_counter, OFFSET + 4);
+    let not_skipped = run(0x9120, |m| m.registers[1..3].copy_from_slice(&[7, 7]));
+    assert_eq!(not_skipped.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn load_value() {
+    let interpreter = run(0x6A5C, |_| ());
+    assert_eq!(interpreter.registers[0xA], 0x5C);
+}
+
+#[test]
+fn add_value_leaves_vf_alone() {
+    let interpreter = run(0x7105, |m| {
+        m.registers[1] = 0x10;
+        m.registers[0xF] = 0x07;
+    });
+    assert_eq!(interpreter.registers[1], 0x15);
+    assert_eq!(interpreter.registers[0xF], 0x07);
+}
+
+#[test]
+fn copy_register() {
+    let interpreter = run(0x8120, |m| m.registers[2] = 0x99);
+    assert_eq!(interpreter.registers[1], 0x99);
+    assert_eq!(interpreter.registers[2], 0x99);
+}
+
+#[test]
+fn logic_operations() {
+    for (opcode, expected) in [(0x8121, 0b1110), (0x8122, 0b1000), (0x8123, 0b0110)] {
+        let interpreter = run(opcode, |m| {
+            m.registers[1] = 0b1100;
+            m.registers[2] = 0b1010;
+        });
+        assert_eq!(interpreter.registers[1], expected, "{opcode:04x}");
+    }
+}
+
+#[test]
+fn logic_operations_vf_reset() {
+    for opcode in [0x8121, 0x8122, 0x8123] {
+        let reset = run(opcode, |m| m.registers[0xF] = 0x05);
+        assert_eq!(reset.registers[0xF], 0x00, "{opcode:04x}");
+
+        let kept = run(opcode, |m| {
+            m.quirks = Quirks::XOCHIP;
+            m.registers[0xF] = 0x05;
+        });
+        assert_eq!(kept.registers[0xF], 0x05, "{opcode:04x}");
+    }
+}
+
+#[test]
+fn add_registers() {
+    let no_carry = run(0x8124, |m| m.registers[1..3].copy_from_slice(&[0x10, 0x20]));
+    assert_eq!(no_carry.registers[1], 0x30);
+    assert_eq!(no_carry.registers[0xF], 0);
+
+    let carry = run(0x8124, |m| m.registers[1..3].copy_from_slice(&[0xFF, 0x02]));
+    assert_eq!(carry.registers[1], 0x01);
+    assert_eq!(carry.registers[0xF], 1);
+}
+
+#[test]
+fn add_registers_with_vf() {
+    // the flag is written last and wins over the sum
+    let x_is_vf = run(0x8F14, |m| {
+        m.registers[0xF] = 0xFF;
+        m.registers[1] = 0x01;
+    });
+    assert_eq!(x_is_vf.registers[0xF], 1);
+
+    let y_is_vf = run(0x81F4, |m| {
+        m.registers[1] = 0xFF;
+        m.registers[0xF] = 0x02;
+    });
+    assert_eq!(y_is_vf.registers[1], 0x01);
+    assert_eq!(y_is_vf.registers[0xF], 1);
+}
+
+#[test]
+fn subtract_registers() {
+    let no_borrow = run(0x8125, |m| m.registers[1..3].copy_from_slice(&[5, 3]));
+    assert_eq!(no_borrow.registers[1], 2);
+    assert_eq!(no_borrow.registers[0xF], 1);
+
+    let equal = run(0x8125, |m| m.registers[1..3].copy_from_slice(&[5, 5]));
+    assert_eq!(equal.registers[1], 0);
+    assert_eq!(equal.registers[0xF], 1);
+
+    let borrow = run(0x8125, |m| m.registers[1..3].copy_from_slice(&[3, 5]));
+    assert_eq!(borrow.registers[1], 0xFE);
+    assert_eq!(borrow.registers[0xF], 0);
+}
+
+#[test]
+fn subtract_registers_with_vf() {
+    let x_is_vf = run(0x8F15, |m| {
+        m.registers[0xF] = 3;
+        m.registers[1] = 5;
+    });
+    assert_eq!(x_is_vf.registers[0xF], 0);
+
+    let y_is_vf = run(0x81F5, |m| {
+        m.registers[1] = 5;
+        m.registers[0xF] = 3;
+    });
+    assert_eq!(y_is_vf.registers[1], 2);
+    assert_eq!(y_is_vf.registers[0xF], 1);
+}
+
+#[test]
+fn subtract_registers_reversed() {
+    let no_borrow = run(0x8127, |m| m.registers[1..3].copy_from_slice(&[3, 5]));
+    assert_eq!(no_borrow.registers[1], 2);
+    assert_eq!(no_borrow.registers[0xF], 1);
+
+    let borrow = run(0x8127, |m| m.registers[1..3].copy_from_slice(&[5, 3]));
+    assert_eq!(borrow.registers[1], 0xFE);
+    assert_eq!(borrow.registers[0xF], 0);
+
+    let x_is_vf = run(0x8F17, |m| {
+        m.registers[0xF] = 3;
+        m.registers[1] = 5;
+    });
+    assert_eq!(x_is_vf.registers[0xF], 1);
+}
+
+#[test]
+fn shift_right() {
+    let shifted_vy = run(0x8126, |m| {
+        m.registers[1..3].copy_from_slice(&[0xF0, 0b101])
+    });
+    assert_eq!(shifted_vy.registers[1], 0b10);
+    assert_eq!(shifted_vy.registers[2], 0b101);
+    assert_eq!(shifted_vy.registers[0xF], 1);
+
+    let shifted_vx = run(0x8126, |m| {
+        m.quirks.shift_vx = true;
+        m.registers[1..3].copy_from_slice(&[0b110, 0b101]);
+    });
+    assert_eq!(shifted_vx.registers[1], 0b11);
+    assert_eq!(shifted_vx.registers[0xF], 0);
+
+    let x_is_vf = run(0x8F26, |m| m.registers[2] = 0b10);
+    assert_eq!(x_is_vf.registers[0xF], 0);
+}
+
+#[test]
+fn shift_left() {
+    let shifted_vy = run(0x812E, |m| m.registers[1..3].copy_from_slice(&[0x01, 0x81]));
+    assert_eq!(shifted_vy.registers[1], 0x02);
+    assert_eq!(shifted_vy.registers[2], 0x81);
+    assert_eq!(shifted_vy.registers[0xF], 1);
+
+    let shifted_vx = run(0x812E, |m| {
+        m.quirks.shift_vx = true;
+        m.registers[1..3].copy_from_slice(&[0x41, 0x81]);
+    });
+    assert_eq!(shifted_vx.registers[1], 0x82);
+    assert_eq!(shifted_vx.registers[0xF], 0);
+
+    let x_is_vf = run(0x8F2E, |m| m.registers[2] = 0x80);
+    assert_eq!(x_is_vf.registers[0xF], 1);
+}
+
+#[test]
+fn load_index() {
+    let interpreter = run(0xA123, |_| ());
+    assert_eq!(interpreter.index, 0x123);
+}
+
+#[test]
+fn jump_with_offset() {
+    let v0 = run(0xB310, |m| {
+        m.registers[0] = 4;
+        m.registers[3] = 8;
+    });
+    assert_eq!(v0.program_counter, 0x314);
+
+    let vx = run(0xB310, |m| {
+        m.quirks.jump_vx = true;
+        m.registers[0] = 4;
+        m.registers[3] = 8;
+    });
+    assert_eq!(vx.program_counter, 0x318);
+}
+
+#[test]
+fn random_is_masked() {
+    assert_eq!(run(0xC100, |m| m.registers[1] = 0xFF).registers[1], 0);
+    for _ in 0..100 {
+        assert_eq!(run(0xC10F, |_| ()).registers[1] & 0xF0, 0);
+    }
+}
+
+#[test]
+fn draw_and_collide() {
+    // the font's 0 at I = 0
+    let mut interpreter = Interpreter::with_program(&[0xD015, 0xD015]);
+    interpreter.registers[0xF] = 0x05;
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.registers[0xF], 0);
+    assert_eq!(lit_pixels(&interpreter), 14);
+    assert!(interpreter.screen[0][..4].iter().all(|&on| on));
+    assert!(!interpreter.screen[1][1]);
+
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.registers[0xF], 1);
+    assert_eq!(lit_pixels(&interpreter), 0);
+}
+
+#[test]
+fn draw_clips_at_edges() {
+    let interpreter = run(0xD125, |m| {
+        m.index = 0x300;
+        m.memory[0x300..0x305].fill(0xFF);
+        m.registers[1] = WIDTH as u8 - 2;
+        m.registers[2] = HEIGHT as u8 - 3;
+    });
+    assert_eq!(lit_pixels(&interpreter), 2 * 3);
+    assert!(interpreter.screen[HEIGHT - 1][WIDTH - 1]);
+    assert!(!interpreter.screen[0][0]);
+}
+
+#[test]
+fn draw_position_wraps() {
+    let interpreter = run(0xD121, |m| {
+        m.index = 0x300;
+        m.memory[0x300] = 0x80;
+        m.registers[1] = WIDTH as u8 + 3;
+        m.registers[2] = HEIGHT as u8 + 1;
+    });
+    assert_eq!(lit_pixels(&interpreter), 1);
+    assert!(interpreter.screen[1][3]);
+}
+
+#[test]
+fn draw_at_vf() {
+    let interpreter = run(0xDF11, |m| {
+        m.index = 0x300;
+        m.memory[0x300] = 0x80;
+        m.registers[0xF] = 10;
+        m.registers[1] = 4;
+    });
+    assert!(interpreter.screen[4][10]);
+    assert_eq!(interpreter.registers[0xF], 0);
+}
+
+#[test]
+fn draw_at_vf_as_y() {
+    let interpreter = run(0xD1F1, |m| {
+        m.index = 0x300;
+        m.memory[0x300] = 0x80;
+        m.registers[1] = 4;
+        m.registers[0xF] = 10;
+    });
+    assert!(interpreter.screen[10][4]);
+    assert_eq!(interpreter.registers[0xF], 0);
+}
+
+#[test]
+fn draw_past_end_of_memory() {
+    let mut interpreter = Interpreter::with_program(&[0xD015]);
+    interpreter.index = 0xFFE;
+    assert_eq!(
+        interpreter.exe(),
+        Err(ExeError::MemoryOutOfBounds {
+            addr: OFFSET,
+            access: 0x1002
+        })
+    );
+}
+
+#[test]
+fn skip_if_key() {
+    let mut pressed = Interpreter::with_program(&[0xE19E]);
+    pressed.registers[1] = 5;
+    pressed.press_key(KeypadKey::Key5);
+    pressed.tick();
+    step(&mut pressed, 1);
+    assert_eq!(pressed.program_counter, OFFSET + 4);
+
+    let not_pressed = run(0xE19E, |m| m.registers[1] = 5);
+    assert_eq!(not_pressed.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn skip_if_not_key() {
+    let mut pressed = Interpreter::with_program(&[0xE1A1]);
+    pressed.registers[1] = 5;
+    pressed.press_key(KeypadKey::Key5);
+    pressed.tick();
+    step(&mut pressed, 1);
+    assert_eq!(pressed.program_counter, OFFSET + 2);
+
+    let not_pressed = run(0xE1A1, |m| m.registers[1] = 5);
+    assert_eq!(not_pressed.program_counter, OFFSET + 4);
+}
+
+#[test]
+fn skip_if_key_uses_low_digit() {
+    let mut interpreter = Interpreter::with_program(&[0xE19E]);
+    interpreter.registers[1] = 0xF5;
+    interpreter.press_key(KeypadKey::Key5);
+    interpreter.tick();
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, OFFSET + 4);
+}
+
+#[test]
+fn skip_if_not_key_uses_low_digit() {
+    let mut interpreter = Interpreter::with_program(&[0xE1A1]);
+    interpreter.registers[1] = 0x3C;
+    interpreter.press_key(KeypadKey::KeyC);
+    interpreter.tick();
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, OFFSET + 2);
+}
+
+#[test]
+fn read_delay_timer() {
+    let interpreter = run(0xF107, |m| m.delay_timer = 0x20);
+    assert_eq!(interpreter.registers[1], 0x20);
+}
+
+#[test]
+fn wait_for_key_release() {
+    let mut interpreter = Interpreter::with_program(&[0xF10A]);
+    step(&mut interpreter, 2);
+    assert_eq!(interpreter.program_counter, OFFSET);
+
+    interpreter.press_key(KeypadKey::KeyA);
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, OFFSET);
+
+    interpreter.release_key(KeypadKey::KeyA);
+    step(&mut interpreter, 1);
+    assert_eq!(interpreter.program_counter, OFFSET + 2);
+    assert_eq!(interpreter.registers[1], 0xA);
+}
+
+#[test]
+fn set_timers() {
+    let delay = run(0xF115, |m| m.registers[1] = 0x30);
+    assert_eq!(delay.delay_timer, 0x30);
+    let sound = run(0xF118, |m| m.registers[1] = 0x40);
+    assert_eq!(sound.sound_timer, 0x40);
+}
+
+#[test]
+fn add_to_index() {
+    let interpreter = run(0xF11E, |m| {
+        m.index = 0x10;
+        m.registers[1] = 5;
+        m.registers[0xF] = 7;
+    });
+    assert_eq!(interpreter.index, 0x15);
+    assert_eq!(interpreter.registers[0xF], 7);
+}
+
+#[test]
+fn add_to_index_overflow() {
+    let overflow = run(0xF11E, |m| {
+        m.quirks.index_overflow = true;
+        m.index = 0xFFE;
+        m.registers[1] = 4;
+    });
+    assert_eq!(overflow.index, 0x1002);
+    assert_eq!(overflow.registers[0xF], 1);
+
+    let no_overflow = run(0xF11E, |m| {
+        m.quirks.index_overflow = true;
+        m.index = 0x10;
+        m.registers[1] = 4;
+        m.registers[0xF] = 7;
+    });
+    assert_eq!(no_overflow.registers[0xF], 0);
+}
+
+#[test]
+fn font_character() {
+    let interpreter = run(0xF129, |m| m.registers[1] = 0xA);
+    assert_eq!(interpreter.index, 50);
+    assert_eq!(&interpreter.memory[50..55], &[0xF0, 0x90, 0xF0, 0x90, 0x90]);
+}
+
+#[test]
+fn binary_coded_decimal() {
+    let interpreter = run(0xF133, |m| {
+        m.index = 0x300;
+        m.registers[1] = 234;
+    });
+    assert_eq!(&interpreter.memory[0x300..0x303], &[2, 3, 4]);
+    assert_eq!(interpreter.index, 0x300);
+
+    let mut out_of_bounds = Interpreter::with_program(&[0xF133]);
+    out_of_bounds.index = 0xFFE;
+    assert!(matches!(
+        out_of_bounds.exe(),
+        Err(ExeError::MemoryOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn store_registers() {
+    let setup = |m: &mut Interpreter| {
+        m.index = 0x300;
+        m.registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+    };
+    let incremented = run(0xF255, setup);
+    assert_eq!(&incremented.memory[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(incremented.index, 0x303);
+
+    let unchanged = run(0xF255, |m| {
+        setup(m);
+        m.quirks.memory_increment = false;
+    });
+    assert_eq!(&unchanged.memory[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(unchanged.index, 0x300);
+}
+
+#[test]
+fn load_registers() {
+    let setup = |m: &mut Interpreter| {
+        m.index = 0x300;
+        m.memory[0x300..0x304].copy_from_slice(&[1, 2, 3, 4]);
+    };
+    let incremented = run(0xF265, setup);
+    assert_eq!(&incremented.registers[..4], &[1, 2, 3, 0]);
+    assert_eq!(incremented.index, 0x303);
+
+    let unchanged = run(0xF265, |m| {
+        setup(m);
+        m.quirks.memory_increment = false;
+    });
+    assert_eq!(&unchanged.registers[..4], &[1, 2, 3, 0]);
+    assert_eq!(unchanged.index, 0x300);
+
+    let mut out_of_bounds = Interpreter::with_program(&[0xFF65]);
+    out_of_bounds.index = 0xFF8;
+    assert_eq!(
+        out_of_bounds.exe(),
+        Err(ExeError::MemoryOutOfBounds {
+            addr: OFFSET,
+            access: 0x1007
+        })
+    );
+}
+
+#[test]
+fn timers_count_down_once_per_tick() {
+    let mut interpreter = Interpreter::with_program(&[]);
+    interpreter.delay_timer = 2;
+    interpreter.sound_timer = 1;
+    interpreter.tick();
+    assert_eq!((interpreter.delay_timer, interpreter.sound_timer), (1, 0));
+    interpreter.tick();
+    assert_eq!((interpreter.delay_timer, interpreter.sound_timer), (0, 0));
+}
+
+#[test]
+fn load_checks_the_program_fits() {
+    let dir = std::env::temp_dir().join("chip8-load-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut interpreter = Interpreter::new();
+    let room = interpreter.memory.len() - OFFSET;
+
+    let fits = dir.join("fits.ch8");
+    std::fs::write(&fits, vec![0xAB; room]).unwrap();
+    interpreter.load(fits.to_str().unwrap()).unwrap();
+    assert_eq!(interpreter.memory[interpreter.memory.len() - 1], 0xAB);
+
+    let too_large = dir.join("too-large.ch8");
+    std::fs::write(&too_large, vec![0; room + 1]).unwrap();
+    let err = interpreter.load(too_large.to_str().unwrap()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "the program is {} bytes, only {room} fit in memory",
+            room + 1
+        )
+    );
+}
@@ -0,0 +1,88 @@
+//! Palettes, the themes and the config file that picks them per ROM.
+
+use chip8::config::Config;
+use chip8::{Interpreter, Palette, WIDTH};
+
+#[test]
+fn themes() {
+    assert_eq!(Palette::default(), Palette::MONO);
+    for (name, palette) in Palette::THEMES {
+        assert_eq!(Palette::theme(name), Some(palette));
+        assert_eq!(Palette::parse(name), Some(palette));
+        assert_ne!(palette.colors[0], palette.colors[1], "{name}");
+    }
+    assert_eq!(Palette::theme("sepia"), None);
+}
+
+#[test]
+fn parse_colors() {
+    let two = Palette::parse("000000,#FFcc00").unwrap();
+    assert_eq!(
+        two.colors,
+        [[0, 0, 0], [0xff, 0xcc, 0], [0xff, 0xcc, 0], [0xff, 0xcc, 0]]
+    );
+    let four = Palette::parse("996600, ffcc00, ff6600, 662200").unwrap();
+    assert_eq!(four, Palette::OCTO);
+    for bad in [
+        "",
+        "000000",
+        "000000,fff",
+        "000000,gggggg",
+        "1,2,3,4,5",
+        "0,0,0,0,0",
+    ] {
+        assert_eq!(Palette::parse(bad), None, "{bad}");
+    }
+}
+
+#[test]
+fn draw_uses_the_palette() {
+    let mut interpreter = Interpreter::new();
+    interpreter.screen[0][1] = true;
+    interpreter.palette = Palette::AMBER;
+    let mut frame = vec![0; WIDTH * 4];
+    // only the first row
+    interpreter.draw(&mut frame);
+    assert_eq!(frame[..4], [0x14, 0x0c, 0x00, 0xff]);
+    assert_eq!(frame[4..8], [0xff, 0xb0, 0x00, 0xff]);
+}
+
+#[test]
+fn config_per_rom() {
+    let config = Config::parse(
+        "# everything amber
+palette = amber
+
+[pong.ch8]
+palette = #000000,#00ff00
+
+[octo.ch8]
+palette = octo
+",
+    )
+    .unwrap();
+    assert_eq!(config.palette_for("roms/tetris.ch8"), Some(Palette::AMBER));
+    assert_eq!(config.palette_for("octo.ch8"), Some(Palette::OCTO));
+    let pong = config.palette_for("games/pong.ch8").unwrap();
+    assert_eq!(pong.colors[1], [0, 0xff, 0]);
+
+    let config = Config::parse("[pong.ch8]\npalette = lcd\n").unwrap();
+    assert_eq!(config.palette_for("tetris.ch8"), None);
+    assert_eq!(config.palette_for("pong.ch8"), Some(Palette::LCD));
+}
+
+#[test]
+fn config_errors() {
+    assert_eq!(
+        Config::parse("palette = sepia").err().unwrap(),
+        "line 1: bad palette 'sepia'"
+    );
+    assert_eq!(
+        Config::parse("\nscale = 2").err().unwrap(),
+        "line 2: unknown setting 'scale'"
+    );
+    assert_eq!(
+        Config::parse("palette").err().unwrap(),
+        "line 1: expected 'key = value'"
+    );
+}
@@ -0,0 +1,100 @@
+//! Pixels fading in and out with `Persistence`, and how they are drawn.
+
+use chip8::config::Config;
+use chip8::{Interpreter, Palette, Persistence};
+
+// brightness of a pixel over frames lit as given
+fn fade(persistence: Persistence, lit: &[bool]) -> Vec<f32> {
+    let mut brightness = 0.0;
+    lit.iter()
+        .map(|&lit| {
+            brightness = persistence.fade(brightness, lit);
+            brightness
+        })
+        .collect()
+}
+
+#[test]
+fn parse() {
+    assert_eq!(Persistence::parse("off"), Some(Persistence::Off));
+    assert_eq!(
+        Persistence::parse("decay:0.5"),
+        Some(Persistence::Decay(0.5))
+    );
+    assert_eq!(Persistence::parse("blend:1"), Some(Persistence::Blend(1.0)));
+    for bad in [
+        "",
+        "decay",
+        "decay:",
+        "decay:1.5",
+        "blend:-0.1",
+        "glow:0.5",
+        "off:1",
+    ] {
+        assert_eq!(Persistence::parse(bad), None, "{bad}");
+    }
+}
+
+#[test]
+fn off_follows_the_screen() {
+    assert_eq!(
+        fade(Persistence::Off, &[true, false, true]),
+        [1.0, 0.0, 1.0]
+    );
+}
+
+#[test]
+fn decay_fades_out_only() {
+    let frames = fade(Persistence::Decay(0.5), &[true, false, false, true, false]);
+    assert_eq!(frames, [1.0, 0.5, 0.25, 1.0, 0.5]);
+}
+
+#[test]
+fn blend_fades_in_and_out() {
+    let frames = fade(Persistence::Blend(0.5), &[true, true, false, false]);
+    assert_eq!(frames, [0.5, 0.75, 0.375, 0.1875]);
+}
+
+#[test]
+fn flicker_is_smoothed() {
+    // a sprite erased and drawn again every other frame stays mostly lit
+    let flicker: Vec<bool> = (0..20).map(|frame| frame % 2 == 0).collect();
+    let decay = fade(Persistence::Decay(0.8), &flicker);
+    assert!(decay[2..].iter().all(|&b| b >= 0.8));
+    let blend = fade(Persistence::Blend(0.8), &flicker);
+    assert!(
+        blend[10..].iter().all(|&b| (0.4..0.6).contains(&b)),
+        "{blend:?}"
+    );
+}
+
+#[test]
+fn draw_mixes_background_and_foreground() {
+    let mut interpreter = Interpreter::with_program(&[0x1200]);
+    interpreter.palette = Palette::parse("000000,ffffff").unwrap();
+    interpreter.persistence = Persistence::Decay(0.5);
+    interpreter.screen[0][0] = true;
+    interpreter.update(&[], &mut []).unwrap();
+    assert_eq!(interpreter.pixel_rgba(0, 0), [0xff, 0xff, 0xff, 0xff]);
+
+    interpreter.screen[0][0] = false;
+    interpreter.update(&[], &mut []).unwrap();
+    assert_eq!(interpreter.pixel_rgba(0, 0), [0x80, 0x80, 0x80, 0xff]);
+    interpreter.update(&[], &mut []).unwrap();
+    assert_eq!(interpreter.pixel_rgba(0, 0), [0x40, 0x40, 0x40, 0xff]);
+    assert_eq!(interpreter.pixel_rgba(1, 0), [0, 0, 0, 0xff]);
+}
+
+#[test]
+fn config() {
+    let config = Config::parse("persistence = decay:0.6\n[pong.ch8]\npersistence = off\n").unwrap();
+    assert_eq!(
+        config.persistence_for("tetris.ch8"),
+        Some(Persistence::Decay(0.6))
+    );
+    assert_eq!(config.persistence_for("pong.ch8"), Some(Persistence::Off));
+    assert_eq!(
+        Config::parse("persistence = decay").err().unwrap(),
+        "line 1: bad persistence 'decay'"
+    );
+}
@@ -0,0 +1,158 @@
+//! The profiler's attribution of cycles to subroutines across calls and returns, and its
+//! report and folded stack output.
+
+use chip8::profile::Profiler;
+use chip8::symbols::SymbolMap;
+use chip8::timing::vip_cycles;
+use chip8::{Interpreter, Timing};
+
+// main calls a, which calls b, then main spins
+const PROGRAM: [u16; 8] = [
+    0x2206, // 200 main: call a
+    0x1202, // 202 spin
+    0x0000, // 204
+    0x6001, // 206 a: v0 := 1
+    0x220C, // 208 call b
+    0x00EE, // 20a return
+    0x6102, // 20c b: v1 := 2
+    0x00EE, // 20e return
+];
+
+fn profile(timing: Timing, frames: usize, symbols: SymbolMap) -> Profiler {
+    let mut interpreter = Interpreter::with_program(&PROGRAM);
+    interpreter.timing = timing;
+    let mut profiler = Profiler::new(symbols);
+    for _ in 0..frames {
+        interpreter.update(&[], &mut [&mut profiler]).unwrap();
+    }
+    profiler
+}
+
+// the subroutine table of the report: name -> (inclusive, exclusive, calls)
+fn subroutines(report: &str) -> Vec<(String, u64, u64, u64)> {
+    report
+        .split("\nsubroutines\n")
+        .nth(1)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (
+                fields[5].to_string(),
+                fields[0].parse().unwrap(),
+                fields[2].parse().unwrap(),
+                fields[4].parse().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn instructions_count_one_cycle_each() {
+    let profiler = profile(Timing::InstructionsPerFrame(10), 1, SymbolMap::default());
+    let report = profiler.report();
+    assert!(
+        report.starts_with("10 cycles, 10 instructions in 1 frames, 40.0% idle\n"),
+        "{report}"
+    );
+    assert!(report.contains("instructions per frame: min 10 avg 10.0 max 10 of 10 IPF"));
+    // a includes b, main includes everything
+    assert_eq!(
+        subroutines(&report),
+        [
+            ("main".to_string(), 10, 5, 0),
+            ("sub_206".to_string(), 5, 3, 1),
+            ("sub_20c".to_string(), 2, 2, 1),
+        ]
+    );
+    assert_eq!(
+        profiler.folded(),
+        "main 5\nmain;sub_206 3\nmain;sub_206;sub_20c 2\n"
+    );
+}
+
+#[test]
+fn vip_timing_counts_machine_cycles() {
+    let profiler = profile(Timing::Vip, 1, SymbolMap::default());
+    let cycles = |opcode| vip_cycles(opcode, &[0; 16]) as u64;
+    let b = cycles(0x6102) + cycles(0x00EE);
+    let a = cycles(0x6001) + cycles(0x220C) + cycles(0x00EE);
+
+    let report = profiler.report();
+    let table = subroutines(&report);
+    assert_eq!(table[1], ("sub_206".to_string(), a + b, a, 1));
+    assert_eq!(table[2], ("sub_20c".to_string(), b, b, 1));
+    let folded = profiler.folded();
+    assert!(folded.contains(&format!("main;sub_206 {a}\n")), "{folded}");
+    assert!(
+        folded.contains(&format!("main;sub_206;sub_20c {b}\n")),
+        "{folded}"
+    );
+
+    // the spinning jumps are where the rest of the frame went
+    let spin = report
+        .lines()
+        .find(|line| line.contains(" 202  1202 "))
+        .unwrap();
+    let fields: Vec<&str> = spin.split_whitespace().collect();
+    let (spin_cycles, count): (u64, u64) = (fields[0].parse().unwrap(), fields[2].parse().unwrap());
+    assert_eq!(spin_cycles, count * cycles(0x1202));
+}
+
+#[test]
+fn unfinished_calls_count_so_far() {
+    // stops inside b, which loops on its first instruction
+    let mut interpreter = Interpreter::with_program(&[0x2204, 0x0000, 0x1204]);
+    interpreter.timing = Timing::InstructionsPerFrame(5);
+    let mut profiler = Profiler::new(SymbolMap::default());
+    interpreter.update(&[], &mut [&mut profiler]).unwrap();
+    assert_eq!(
+        subroutines(&profiler.report()),
+        [
+            ("main".to_string(), 5, 1, 0),
+            ("sub_204".to_string(), 4, 4, 1)
+        ]
+    );
+}
+
+#[test]
+fn recursion_counts_once() {
+    // calls itself three times through v0, then returns all the way out
+    let program = [
+        0x2206, // 200 call r
+        0x1202, // 202 spin
+        0x0000, // 204
+        0x7001, // 206 r: v0 += 1
+        0x3003, // 208 if v0 != 3 then
+        0x2206, // 20a call r
+        0x00EE, // 20c return
+    ];
+    let mut interpreter = Interpreter::with_program(&program);
+    interpreter.timing = Timing::InstructionsPerFrame(14);
+    let mut profiler = Profiler::new(SymbolMap::default());
+    interpreter.update(&[], &mut [&mut profiler]).unwrap();
+    let table = subroutines(&profiler.report());
+    // 3 levels of 3 or 4 instructions, all inside the outermost call
+    assert_eq!(table[1], ("sub_206".to_string(), 11, 11, 3));
+    assert_eq!(
+        profiler.folded(),
+        "main 3\nmain;sub_206 4\nmain;sub_206;sub_206 4\nmain;sub_206;sub_206;sub_206 3\n"
+    );
+}
+
+#[test]
+fn labels_name_subroutines() {
+    let mut symbols = SymbolMap::new("game.8o");
+    symbols.add_label(0x200, "start");
+    symbols.add_label(0x206, "draw");
+    let profiler = profile(Timing::InstructionsPerFrame(10), 2, symbols);
+    // b has no label of its own, only an offset from draw
+    assert_eq!(
+        profiler.folded(),
+        "start 15\nstart;draw 3\nstart;draw;sub_20c 2\n"
+    );
+    assert!(profiler
+        .report()
+        .contains(" 206  6001 v0 := 0x01               draw"));
+}
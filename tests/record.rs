@@ -0,0 +1,100 @@
+//! Recordings of a program that flips a sprite every 10 frames with the buzzer on for 30.
+
+use std::path::PathBuf;
+
+use chip8::headless::{self, StopCondition};
+use chip8::record::{Outputs, Recorder, SAMPLE_RATE};
+use chip8::{Interpreter, TARGET_FPS};
+
+const FRAMES: u64 = 60;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as u64 / TARGET_FPS) as usize;
+
+const PROGRAM: [u16; 9] = [
+    0x601E, // V0 := 30
+    0xF018, // buzzer := V0
+    0x610A, // V1 := 10
+    0xD001, // sprite V0 V0 1
+    0xF115, // delay := V1
+    0xF207, // V2 := delay
+    0x3200, // if V2 != 0 then
+    0x120A, //   jump back to V2 := delay
+    0x1206, // jump back to the sprite
+];
+
+fn record(name: &str) -> (PathBuf, Outputs) {
+    let dir = std::env::temp_dir().join(format!("chip8-record-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let outputs = Outputs {
+        gif: Some(dir.join("run.gif")),
+        frames: Some(dir.join("frames")),
+        wav: Some(dir.join("run.wav")),
+        scale: 2,
+    };
+    let mut interpreter = Interpreter::with_program(&PROGRAM);
+    let mut recorder = Recorder::start(&outputs).unwrap();
+    let mut stop = StopCondition::default();
+    let (frames, result) = headless::run_with(
+        &mut interpreter,
+        FRAMES,
+        &[],
+        &mut stop,
+        &mut [&mut recorder],
+    );
+    result.unwrap();
+    assert_eq!((frames, recorder.frames()), (FRAMES, FRAMES));
+    recorder.finish().unwrap();
+    (dir, outputs)
+}
+
+#[test]
+fn gif_has_a_frame_per_change() {
+    let (_, outputs) = record("gif");
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let file = std::fs::File::open(outputs.gif.unwrap()).unwrap();
+    let mut decoder = options.read_info(file).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (128, 64));
+
+    let mut delays = vec![];
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.palette.as_ref().map(|p| p.len()), Some(6));
+        delays.push(frame.delay);
+    }
+    // the sprite is drawn in the first frame and flips every 10 frames after it
+    assert_eq!(delays.len(), 6);
+    // the delays add up to a second
+    assert_eq!(delays.iter().sum::<u16>(), 100);
+}
+
+#[test]
+fn frames_are_numbered_pngs() {
+    let (dir, _) = record("frames");
+    let mut names: Vec<String> = std::fs::read_dir(dir.join("frames"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), FRAMES as usize);
+    assert_eq!(names[0], "000000.png");
+    assert_eq!(names[59], "000059.png");
+}
+
+#[test]
+fn wav_follows_the_sound_timer() {
+    let (_, outputs) = record("wav");
+    let mut reader = hound::WavReader::open(outputs.wav.unwrap()).unwrap();
+    assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+    let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    assert_eq!(samples.len(), FRAMES as usize * SAMPLES_PER_FRAME);
+
+    let frame = |n: usize| &samples[n * SAMPLES_PER_FRAME..(n + 1) * SAMPLES_PER_FRAME];
+    // on for the 30 frames the timer counts down from 30, then silent
+    for n in 0..30 {
+        assert!(frame(n).iter().any(|&s| s > 0), "frame {n}");
+        assert!(frame(n).iter().any(|&s| s < 0), "frame {n}");
+    }
+    for n in 30..FRAMES as usize {
+        assert!(frame(n).iter().all(|&s| s == 0), "frame {n}");
+    }
+}
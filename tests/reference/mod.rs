@@ -0,0 +1,301 @@
+//! A second implementation of the instruction set, written from the instruction descriptions
+//! rather than from `Interpreter::exe`, that the interpreter is compared against step by step.
+//! Shared by the differential tests and the fuzz targets in `fuzz/`.
+
+use chip8::{ExeError, Interpreter, Quirks, HEIGHT, OFFSET, WIDTH};
+
+const MEMORY_SIZE: usize = 4096;
+const STACK_SIZE: usize = 16;
+const FONT_START: usize = 0;
+const FONT_HEIGHT: u16 = 5;
+
+pub struct Machine {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: usize,
+    pub stack: Vec<u16>,
+    pub memory: Vec<u8>,
+    pub screen: [[bool; WIDTH]; HEIGHT],
+    pub delay: u8,
+    pub sound: u8,
+    pub quirks: Quirks,
+}
+
+impl Machine {
+    /// A copy of the interpreter's state.
+    pub fn of(interpreter: &Interpreter) -> Self {
+        Self {
+            v: interpreter.registers,
+            i: interpreter.index,
+            pc: interpreter.program_counter,
+            stack: interpreter.stack.clone(),
+            memory: interpreter.memory.clone(),
+            screen: interpreter.screen,
+            delay: interpreter.delay_timer,
+            sound: interpreter.sound_timer,
+            quirks: interpreter.quirks,
+        }
+    }
+
+    fn check(&self, addr: usize, start: usize, len: usize) -> Result<(), ExeError> {
+        match start + len <= MEMORY_SIZE {
+            true => Ok(()),
+            false => Err(ExeError::MemoryOutOfBounds {
+                addr,
+                access: start + len - 1,
+            }),
+        }
+    }
+
+    /// Execute one instruction. No keys are ever down, so `FX0A` waits forever.
+    pub fn step(&mut self) -> Result<(), ExeError> {
+        let addr = self.pc;
+        self.check(addr, addr, 2)?;
+        let (high, low) = (self.memory[addr], self.memory[addr + 1]);
+        let opcode = u16::from_be_bytes([high, low]);
+        let x = (high & 0xF) as usize;
+        let y = (low >> 4) as usize;
+        let nnn = opcode & 0xFFF;
+        let unknown = ExeError::UnknownOpcode { addr, opcode };
+        self.pc += 2;
+
+        match high >> 4 {
+            0x0 => match opcode {
+                0x00E0 => self.screen = [[false; WIDTH]; HEIGHT],
+                0x00EE => match self.stack.pop() {
+                    Some(ret) => self.pc = ret as usize,
+                    None => return Err(ExeError::StackUnderflow { addr }),
+                },
+                _ => return Err(unknown),
+            },
+            0x1 => self.pc = nnn as usize,
+            0x2 => {
+                if self.stack.len() == STACK_SIZE {
+                    return Err(ExeError::StackOverflow { addr });
+                }
+                self.stack.push(self.pc as u16);
+                self.pc = nnn as usize;
+            }
+            0x3 => self.skip_if(self.v[x] == low),
+            0x4 => self.skip_if(self.v[x] != low),
+            // like the VIP, the last digit of 5XY0 and 9XY0 isn't decoded
+            0x5 => self.skip_if(self.v[x] == self.v[y]),
+            0x9 => self.skip_if(self.v[x] != self.v[y]),
+            0x6 => self.v[x] = low,
+            0x7 => self.v[x] = self.v[x].wrapping_add(low),
+            0x8 => self.alu(x, y, low & 0xF).ok_or(unknown)?,
+            0xA => self.i = nnn,
+            0xB => {
+                let offset = if self.quirks.jump_vx { x } else { 0 };
+                self.pc = nnn as usize + self.v[offset] as usize;
+            }
+            0xC => self.v[x] = rand::random::<u8>() & low,
+            0xD => self.draw(addr, x, y, (low & 0xF) as usize)?,
+            0xE => match low {
+                0x9E => (),
+                0xA1 => self.pc += 2,
+                _ => return Err(unknown),
+            },
+            0xF => match low {
+                0x07 => self.v[x] = self.delay,
+                0x0A => self.pc = addr,
+                0x15 => self.delay = self.v[x],
+                0x18 => self.sound = self.v[x],
+                0x1E => {
+                    let sum = self.i as u32 + self.v[x] as u32;
+                    if self.quirks.index_overflow {
+                        self.v[0xF] = (self.i < 0x1000 && sum >= 0x1000) as u8;
+                    }
+                    self.i = sum as u16;
+                }
+                // the whole register is used, not just the digit
+                0x29 => self.i = FONT_START as u16 + self.v[x] as u16 * FONT_HEIGHT,
+                0x33 => {
+                    let i = self.i as usize;
+                    self.check(addr, i, 3)?;
+                    let value = self.v[x];
+                    self.memory[i..i + 3].copy_from_slice(&[
+                        value / 100,
+                        value / 10 % 10,
+                        value % 10,
+                    ]);
+                }
+                0x55 => {
+                    let i = self.i as usize;
+                    self.check(addr, i, x + 1)?;
+                    self.memory[i..=i + x].copy_from_slice(&self.v[..=x]);
+                    if self.quirks.memory_increment {
+                        self.i += x as u16 + 1;
+                    }
+                }
+                0x65 => {
+                    let i = self.i as usize;
+                    self.check(addr, i, x + 1)?;
+                    self.v[..=x].copy_from_slice(&self.memory[i..=i + x]);
+                    if self.quirks.memory_increment {
+                        self.i += x as u16 + 1;
+                    }
+                }
+                _ => return Err(unknown),
+            },
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    // 8XYN, `None` for the values of N that aren't instructions
+    fn alu(&mut self, x: usize, y: usize, n: u8) -> Option<()> {
+        let (vx, vy) = (self.v[x], self.v[y]);
+        let (result, flag) = match n {
+            0x0 => (vy, None),
+            0x1..=0x3 => {
+                let result = match n {
+                    0x1 => vx | vy,
+                    0x2 => vx & vy,
+                    _ => vx ^ vy,
+                };
+                (result, self.quirks.vf_reset.then_some(0))
+            }
+            0x4 => {
+                let sum = vx as u16 + vy as u16;
+                (sum as u8, Some((sum > 0xFF) as u8))
+            }
+            0x5 => (vx.wrapping_sub(vy), Some((vx >= vy) as u8)),
+            0x7 => (vy.wrapping_sub(vx), Some((vy >= vx) as u8)),
+            0x6 | 0xE => {
+                let source = if self.quirks.shift_vx { vx } else { vy };
+                match n {
+                    0x6 => (source >> 1, Some(source & 1)),
+                    _ => (source << 1, Some(source >> 7)),
+                }
+            }
+            _ => return None,
+        };
+        self.v[x] = result;
+        if let Some(flag) = flag {
+            self.v[0xF] = flag;
+        }
+        Some(())
+    }
+
+    fn draw(&mut self, addr: usize, x: usize, y: usize, height: usize) -> Result<(), ExeError> {
+        let left = self.v[x] as usize % WIDTH;
+        let top = self.v[y] as usize % HEIGHT;
+        let rows = height.min(HEIGHT - top);
+        self.v[0xF] = 0;
+        self.check(addr, self.i as usize, rows)?;
+
+        for row in 0..rows {
+            let byte = self.memory[self.i as usize + row];
+            for column in 0..8 {
+                if left + column >= WIDTH {
+                    break;
+                }
+                if byte & (0x80 >> column) != 0 {
+                    let pixel = &mut self.screen[top + row][left + column];
+                    if *pixel {
+                        self.v[0xF] = 1;
+                    }
+                    *pixel = !*pixel;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// How the interpreter's state differs from this one, if it does.
+    pub fn diff(&self, interpreter: &Interpreter) -> Option<String> {
+        let mut differences = vec![];
+        if self.v != interpreter.registers {
+            differences.push(format!(
+                "registers {:02x?} != {:02x?}",
+                interpreter.registers, self.v
+            ));
+        }
+        if self.i != interpreter.index {
+            differences.push(format!("I {:03x} != {:03x}", interpreter.index, self.i));
+        }
+        if self.pc != interpreter.program_counter {
+            differences.push(format!(
+                "PC {:03x} != {:03x}",
+                interpreter.program_counter, self.pc
+            ));
+        }
+        if self.stack != interpreter.stack {
+            differences.push(format!(
+                "stack {:03x?} != {:03x?}",
+                interpreter.stack, self.stack
+            ));
+        }
+        if (self.delay, self.sound) != (interpreter.delay_timer, interpreter.sound_timer) {
+            differences.push(format!(
+                "timers {:?} != {:?}",
+                (interpreter.delay_timer, interpreter.sound_timer),
+                (self.delay, self.sound)
+            ));
+        }
+        if let Some(addr) = (0..MEMORY_SIZE).find(|&a| self.memory[a] != interpreter.memory[a]) {
+            differences.push(format!(
+                "memory at {addr:03x} {:02x} != {:02x}",
+                interpreter.memory[addr], self.memory[addr]
+            ));
+        }
+        if self.screen != interpreter.screen {
+            differences.push("screen".to_string());
+        }
+        (!differences.is_empty()).then(|| differences.join(", "))
+    }
+}
+
+/// Run `program` from `OFFSET` on both the interpreter and the reference for up to `steps`
+/// instructions, or until the first error, and describe the first difference between them.
+pub fn compare(
+    program: &[u8],
+    quirks: Quirks,
+    registers: [u8; 16],
+    index: u16,
+    steps: usize,
+) -> Result<(), String> {
+    let mut interpreter = Interpreter::new();
+    interpreter.load_rom(&program[..program.len().min(MEMORY_SIZE - OFFSET)]);
+    interpreter.quirks = quirks;
+    interpreter.registers = registers;
+    interpreter.index = index;
+    let mut reference = Machine::of(&interpreter);
+
+    for step in 0..steps {
+        let addr = interpreter.program_counter;
+        let opcode = interpreter
+            .memory
+            .get(addr..addr + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]));
+        let actual = interpreter.exe();
+        let expected = reference.step();
+        let context = || format!("step {step} at {addr:03x}, opcode {opcode:04x?}");
+
+        if actual != expected {
+            return Err(format!("{}: {actual:?} != {expected:?}", context()));
+        }
+        if actual.is_err() {
+            return Ok(());
+        }
+        // random numbers can only be checked against the mask
+        if let Some(opcode) = opcode.filter(|op| op >> 12 == 0xC) {
+            let x = (opcode >> 8 & 0xF) as usize;
+            if interpreter.registers[x] & !(opcode as u8) != 0 {
+                return Err(format!("{}: random number outside the mask", context()));
+            }
+            reference.v[x] = interpreter.registers[x];
+        }
+        if let Some(diff) = reference.diff(&interpreter) {
+            return Err(format!("{}: {diff}", context()));
+        }
+    }
+    Ok(())
+}
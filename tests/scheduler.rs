@@ -0,0 +1,132 @@
+//! Frames due from `Scheduler` for a clock moved by hand, at every speed.
+
+use std::time::{Duration, Instant};
+
+use chip8::scheduler::{Scheduler, Speed, MAX_CATCH_UP};
+
+fn millis(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn runs_at_exactly_the_rate() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    let mut frames = 0;
+    // woken up at uneven times, a bit more than once a millisecond
+    for tick in 1..=10_000 {
+        frames += scheduler.frames_due(start + Duration::from_micros(tick * 1_003));
+    }
+    // 10.03 seconds
+    assert_eq!(frames, 601);
+    assert_eq!(scheduler.dropped(), 0);
+}
+
+#[test]
+fn no_drift_over_a_long_run() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    let mut frames = 0;
+    // an hour, 50 ms at a time
+    for step in 1..=72_000 {
+        frames += scheduler.frames_due(start + millis(step * 50));
+    }
+    assert_eq!(frames, 60 * 3600);
+}
+
+#[test]
+fn next_frame() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    // 1/60 s, rounded up to the nanosecond
+    assert_eq!(
+        scheduler.next_frame(),
+        start + Duration::from_nanos(16_666_667)
+    );
+    assert_eq!(scheduler.frames_due(start + millis(10)), 0);
+    assert_eq!(
+        scheduler.next_frame(),
+        start + Duration::from_nanos(16_666_667)
+    );
+    assert_eq!(scheduler.frames_due(scheduler.next_frame()), 1);
+    assert_eq!(
+        scheduler.next_frame(),
+        start + Duration::from_nanos(33_333_334)
+    );
+}
+
+#[test]
+fn catch_up_is_limited() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    assert_eq!(scheduler.frames_due(start + millis(50)), 3);
+    // the host stalled for a second
+    assert_eq!(scheduler.frames_due(start + millis(1050)), MAX_CATCH_UP);
+    assert_eq!(scheduler.dropped(), 60 - MAX_CATCH_UP);
+    // and carries on from where it is
+    assert_eq!(scheduler.frames_due(start + millis(1067)), 1);
+}
+
+#[test]
+fn clock_going_backwards_runs_nothing() {
+    let start = Instant::now() + millis(100);
+    let mut scheduler = Scheduler::new(60, start);
+    assert_eq!(scheduler.frames_due(start - millis(50)), 0);
+}
+
+#[test]
+fn speed_scales_frames() {
+    let start = Instant::now();
+    for (speed, frames) in [
+        (Speed::Quarter, 15),
+        (Speed::Half, 30),
+        (Speed::Normal, 60),
+        (Speed::Double, 120),
+        (Speed::Quadruple, 240),
+    ] {
+        let mut scheduler = Scheduler::new(60, start);
+        scheduler.set_speed(speed);
+        let total: u64 = (1..=100)
+            .map(|step| scheduler.frames_due(start + millis(step * 10)))
+            .sum();
+        assert_eq!(total, frames, "{speed:?}");
+        assert_eq!(scheduler.dropped(), 0, "{speed:?}");
+    }
+}
+
+#[test]
+fn slow_speed_waits_longer() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    scheduler.set_speed(Speed::Quarter);
+    assert_eq!(
+        scheduler.next_frame(),
+        start + Duration::from_nanos(66_666_667)
+    );
+    scheduler.set_speed(Speed::Quadruple);
+    assert_eq!(
+        scheduler.next_frame(),
+        start + Duration::from_nanos(4_166_667)
+    );
+}
+
+#[test]
+fn unlimited_is_always_due() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(60, start);
+    scheduler.set_speed(Speed::Unlimited);
+    assert_eq!(scheduler.frames_due(start + millis(1)), u64::MAX);
+    assert_eq!(scheduler.next_frame(), start + millis(1));
+    assert_eq!(scheduler.dropped(), 0);
+}
+
+#[test]
+fn speed_steps() {
+    assert_eq!(Speed::default(), Speed::Normal);
+    assert_eq!(Speed::Normal.faster(), Speed::Double);
+    assert_eq!(Speed::Quadruple.faster(), Speed::Unlimited);
+    assert_eq!(Speed::Unlimited.faster(), Speed::Unlimited);
+    assert_eq!(Speed::Unlimited.slower(), Speed::Quadruple);
+    assert_eq!(Speed::Half.slower(), Speed::Quarter);
+    assert_eq!(Speed::Quarter.slower(), Speed::Quarter);
+}
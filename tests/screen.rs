@@ -0,0 +1,84 @@
+//! PNG export of the screen and the names screenshots are saved under.
+
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use chip8::{headless, screen, Interpreter, HEIGHT, WIDTH};
+
+// width, height and RGBA pixels of a PNG
+fn decode(path: &Path) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    data.truncate(info.buffer_size());
+    (info.width, info.height, data)
+}
+
+fn pixel(data: &[u8], width: u32, x: usize, y: usize) -> &[u8] {
+    let offset = (y * width as usize + x) * 4;
+    &data[offset..offset + 4]
+}
+
+#[test]
+fn png_has_the_colors_of_draw() {
+    let mut interpreter = Interpreter::new();
+    interpreter.screen[1][2] = true;
+    let mut frame = vec![0; WIDTH * HEIGHT * 4];
+    interpreter.draw(&mut frame);
+    let (on, off) = (&frame[(WIDTH + 2) * 4..][..4], &frame[..4]);
+
+    let dir = std::env::temp_dir().join("chip8-screen-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    for scale in [1, 3] {
+        let path = dir.join(format!("scale-{scale}.png"));
+        screen::write_png(&interpreter, &path, scale).unwrap();
+        let (width, height, data) = decode(&path);
+        assert_eq!(
+            (width, height),
+            ((WIDTH * scale) as u32, (HEIGHT * scale) as u32)
+        );
+        // the corners of pixel 2, 1
+        for (x, y) in [(2 * scale, scale), (3 * scale - 1, 2 * scale - 1)] {
+            assert_eq!(pixel(&data, width, x, y), on, "scale {scale} at {x},{y}");
+        }
+        assert_eq!(pixel(&data, width, 0, 0), off);
+        assert_eq!(pixel(&data, width, 3 * scale, scale), off);
+    }
+}
+
+#[test]
+fn screenshot_names() {
+    let dir = Path::new("shots");
+    let time = UNIX_EPOCH + Duration::from_millis(1_792_331_445_042);
+    assert_eq!(
+        screen::capture_path(dir, "roms/Space Invaders.ch8", time, "png"),
+        dir.join("Space Invaders-20261018-135045.042.png")
+    );
+    assert_eq!(
+        screen::capture_path(dir, "pong", UNIX_EPOCH, "png"),
+        dir.join("pong-19700101-000000.000.png")
+    );
+    // a leap day
+    let time = UNIX_EPOCH + Duration::from_secs(951_782_400);
+    assert_eq!(
+        screen::capture_path(dir, "pong.ch8", time, "png"),
+        dir.join("pong-20000229-000000.000.png")
+    );
+}
+
+#[test]
+fn headless_screenshot() {
+    let dir = std::env::temp_dir().join("chip8-screenshot-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let interpreter = Interpreter::new();
+    let path = headless::screenshot(&interpreter, "roms/test.ch8", &dir, 2).unwrap();
+    let name = path.file_name().unwrap().to_string_lossy();
+    assert!(
+        name.starts_with("test-") && name.ends_with(".png"),
+        "{name}"
+    );
+    assert_eq!(decode(&path).0, (WIDTH * 2) as u32);
+    std::fs::remove_file(path).unwrap();
+}
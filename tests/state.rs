@@ -0,0 +1,97 @@
+//! Save states.
+
+use chip8::state::StateError;
+use chip8::{Interpreter, KeypadKey, Persistence};
+
+// counts up in V0 and draws its last digit, once a frame with the display wait
+fn counter() -> Interpreter {
+    let mut interpreter = Interpreter::with_program(&[0x7001, 0xF029, 0x00E0, 0xD125, 0x1200]);
+    interpreter.quirks.display_wait = true;
+    interpreter.persistence = Persistence::Decay(0.5);
+    interpreter
+}
+
+fn run(interpreter: &mut Interpreter, frames: usize) {
+    for _ in 0..frames {
+        interpreter.update(&[], &mut []).unwrap();
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut interpreter = counter();
+    run(&mut interpreter, 5);
+    interpreter.press_key(KeypadKey::Key7);
+    interpreter.stack.push(0x246);
+    let state = interpreter.save_state();
+
+    let mut loaded = counter();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+    assert_eq!(loaded.registers, interpreter.registers);
+    assert_eq!(loaded.stack, vec![0x246]);
+    loaded.stack.clear();
+    interpreter.stack.clear();
+
+    // both carry on the same, fading pixels and held keys included
+    run(&mut interpreter, 7);
+    run(&mut loaded, 7);
+    assert_eq!(loaded.save_state(), interpreter.save_state());
+    let (mut a, mut b) = (vec![0; 64 * 32 * 4], vec![0; 64 * 32 * 4]);
+    interpreter.draw(&mut a);
+    loaded.draw(&mut b);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn trailing_bytes_are_ignored() {
+    let mut interpreter = counter();
+    run(&mut interpreter, 3);
+    let mut state = interpreter.save_state();
+    state.resize(state.len() + 100, 0);
+    let mut loaded = counter();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.registers[0], 3);
+}
+
+#[test]
+fn bad_states() {
+    let mut interpreter = counter();
+    run(&mut interpreter, 2);
+    let state = interpreter.save_state();
+    let before = interpreter.save_state();
+
+    assert_eq!(
+        interpreter.load_state(b"not a state"),
+        Err(StateError::NotAState)
+    );
+    let mut version = state.clone();
+    version[4] = 9;
+    assert_eq!(
+        interpreter.load_state(&version),
+        Err(StateError::Version(9))
+    );
+    assert_eq!(
+        interpreter.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+    let mut memory_size = state.clone();
+    memory_size[5] = 1;
+    assert_eq!(
+        interpreter.load_state(&memory_size),
+        Err(StateError::Invalid)
+    );
+    // the first pixel, a bool
+    let mut pixel = state.clone();
+    pixel[9 + 4096] = 2;
+    assert_eq!(interpreter.load_state(&pixel), Err(StateError::Invalid));
+    // the stack length, after the screen, PC and I
+    let mut stack = state.clone();
+    let at = 9 + 4096 + 64 * 32 + 4;
+    stack[at..at + 2].copy_from_slice(&17u16.to_le_bytes());
+    assert_eq!(interpreter.load_state(&stack), Err(StateError::Invalid));
+
+    // nothing was changed by the failures
+    assert_eq!(interpreter.save_state(), before);
+    assert_eq!(StateError::Truncated.to_string(), "save state is cut short");
+}
@@ -0,0 +1,174 @@
+//! How many instructions `Interpreter::update` runs per frame under each `Timing`, and the
+//! display wait quirk ending frames early.
+
+use chip8::timing::vip_cycles;
+use chip8::{ExeError, Interpreter, Observer, Quirks, Timing, IPF, OFFSET};
+
+#[derive(Default)]
+struct Counter {
+    frames: Vec<u64>,
+    instructions: u64,
+    stop_at: Option<u64>,
+}
+
+impl Observer for Counter {
+    fn before_exe(&mut self, _interpreter: &Interpreter) -> bool {
+        self.stop_at == Some(self.instructions)
+    }
+
+    fn after_exe(
+        &mut self,
+        _interpreter: &Interpreter,
+        _addr: usize,
+        _result: Result<(), ExeError>,
+    ) {
+        self.instructions += 1;
+    }
+
+    fn end_frame(&mut self, _interpreter: &Interpreter) {
+        self.frames.push(self.instructions);
+        self.instructions = 0;
+    }
+}
+
+// a jump to itself, run for `frames` frames
+fn run(timing: Timing, frames: usize) -> Vec<u64> {
+    let mut interpreter = Interpreter::with_program(&[0x1000 | OFFSET as u16]);
+    interpreter.timing = timing;
+    let mut counter = Counter::default();
+    for _ in 0..frames {
+        interpreter.update(&[], &mut [&mut counter]).unwrap();
+    }
+    counter.frames
+}
+
+#[test]
+fn parse() {
+    assert_eq!(
+        Timing::parse("ipf:20"),
+        Some(Timing::InstructionsPerFrame(20))
+    );
+    assert_eq!(
+        Timing::parse("ips:700"),
+        Some(Timing::InstructionsPerSecond(700))
+    );
+    assert_eq!(Timing::parse("vip"), Some(Timing::Vip));
+    for text in ["", "ipf", "ipf:", "ips:-1", "vip:1", "fast"] {
+        assert_eq!(Timing::parse(text), None, "{text}");
+    }
+}
+
+#[test]
+fn instructions_per_frame() {
+    assert_eq!(Timing::default(), Timing::InstructionsPerFrame(IPF));
+    assert_eq!(run(Timing::default(), 3), vec![IPF as u64; 3]);
+    assert_eq!(run(Timing::InstructionsPerFrame(7), 3), vec![7; 3]);
+}
+
+#[test]
+fn instructions_per_second_carry_over_frames() {
+    // one and a half instructions per frame
+    let frames = run(Timing::InstructionsPerSecond(90), 60);
+    assert_eq!(frames.iter().sum::<u64>(), 90);
+    assert!(frames.iter().all(|&n| n == 1 || n == 2), "{frames:?}");
+
+    // fewer than one per frame
+    let frames = run(Timing::InstructionsPerSecond(15), 60);
+    assert_eq!(frames.iter().sum::<u64>(), 15);
+    assert_eq!(frames[..4], [1, 0, 0, 0]);
+}
+
+#[test]
+fn vip_cycles_per_frame() {
+    // 2598 cycles left per frame after the display, 80 for each jump
+    let frames = run(Timing::Vip, 60);
+    let total: u64 = frames.iter().sum();
+    // the last instruction of a frame may overdraw, and the next frame pays for it
+    assert_eq!(total, (2598 * 60_u64).div_ceil(80));
+    assert!(frames.iter().all(|&n| n == 32 || n == 33), "{frames:?}");
+}
+
+#[test]
+fn vip_cycles_from_the_timing_table() {
+    // 68 to fetch and decode, then what the instruction's routine takes
+    let registers = [0; 16];
+    for (opcode, cycles) in [
+        (0x00E0, 68 + 24),
+        (0x00EE, 68 + 10),
+        (0x1200, 68 + 12),
+        (0x2200, 68 + 26),
+        (0x6012, 68 + 6),
+        (0x7012, 68 + 10),
+        (0x8124, 68 + 44),
+        (0xC0FF, 68 + 36),
+        (0xF015, 68 + 10),
+        (0xF029, 68 + 20),
+        (0xF055, 68 + 14 + 14),
+    ] {
+        assert_eq!(vip_cycles(opcode, &registers), cycles, "{opcode:04x}");
+    }
+}
+
+#[test]
+fn vip_sprite_cost_depends_on_height_and_alignment() {
+    let mut registers = [0; 16];
+    let aligned = vip_cycles(0xD015, &registers);
+    assert!(vip_cycles(0xD01F, &registers) > aligned);
+    assert!(vip_cycles(0xD011, &registers) < aligned);
+
+    registers[0] = 8;
+    assert_eq!(vip_cycles(0xD015, &registers), aligned);
+    registers[0] = 9;
+    let unaligned = vip_cycles(0xD015, &registers);
+    assert!(unaligned > aligned);
+    registers[0] = 15;
+    assert!(vip_cycles(0xD015, &registers) > unaligned);
+}
+
+#[test]
+fn vip_costs_depend_on_operands() {
+    let mut registers = [0; 16];
+    assert!(vip_cycles(0xF055, &registers) < vip_cycles(0xFF55, &registers));
+    assert!(vip_cycles(0xF065, &registers) < vip_cycles(0xFF65, &registers));
+    let zero = vip_cycles(0xF033, &registers);
+    registers[0] = 199;
+    assert!(vip_cycles(0xF033, &registers) > zero);
+}
+
+#[test]
+fn stopped_frame_does_not_carry_budget() {
+    let mut interpreter = Interpreter::with_program(&[0x1000 | OFFSET as u16]);
+    interpreter.timing = Timing::InstructionsPerFrame(10);
+    let mut counter = Counter {
+        stop_at: Some(3),
+        ..Counter::default()
+    };
+    interpreter.update(&[], &mut [&mut counter]).unwrap();
+    counter.stop_at = None;
+    interpreter.update(&[], &mut [&mut counter]).unwrap();
+    assert_eq!(counter.frames, vec![3, 10]);
+}
+
+#[test]
+fn display_wait_ends_frame_after_draw() {
+    // a sprite drawn over and over
+    let program = [0xD001, 0x1000 | OFFSET as u16];
+    let display_wait = Quirks {
+        display_wait: true,
+        ..Quirks::CHIP8
+    };
+    for (quirks, expected) in [
+        (display_wait, [1, 2, 2]),
+        // off unless asked for, even for the VIP's own interpreter
+        (Quirks::CHIP8, [IPF as u64; 3]),
+        (Quirks::XOCHIP, [IPF as u64; 3]),
+    ] {
+        let mut interpreter = Interpreter::with_program(&program);
+        interpreter.quirks = quirks;
+        let mut counter = Counter::default();
+        for _ in 0..3 {
+            interpreter.update(&[], &mut [&mut counter]).unwrap();
+        }
+        assert_eq!(counter.frames, expected, "{quirks:?}");
+    }
+}
@@ -0,0 +1,128 @@
+//! Execution traces of small programs run through `Interpreter::update`, with each filter and
+//! with the ring buffer that's only written when an instruction fails.
+
+use chip8::trace::{parse_classes, parse_range, TraceFilter, Tracer};
+use chip8::Interpreter;
+
+// run `frames` frames of `program` with a tracer, returning the lines it wrote
+fn trace(
+    name: &str,
+    program: &[u16],
+    frames: usize,
+    filter: TraceFilter,
+    ring: Option<usize>,
+) -> Vec<String> {
+    let dir = std::env::temp_dir().join("chip8-trace-test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.txt"));
+    let mut tracer = Tracer::new(path.to_str(), filter, ring).unwrap();
+    let mut interpreter = Interpreter::with_program(program);
+    for _ in 0..frames {
+        if interpreter.update(&[], &mut [&mut tracer]).is_err() {
+            break;
+        }
+    }
+    // written out when dropped
+    drop(tracer);
+    let text = std::fs::read_to_string(path).unwrap();
+    text.lines().map(str::to_string).collect()
+}
+
+// sets a few registers and then reads the delay timer in a loop
+const PROGRAM: [u16; 6] = [
+    0x6005, // v0 := 5
+    0xA300, // i := 0x300
+    0x6101, // v1 := 1
+    0xF115, // delay := v1
+    0xF107, // v1 := delay
+    0x1208, // jump 0x208
+];
+
+#[test]
+fn every_instruction_with_its_changes() {
+    let lines = trace("all", &PROGRAM, 1, TraceFilter::default(), None);
+    assert_eq!(
+        lines[..7],
+        [
+            "     0 200: 6005 v0 := 0x05               v0 00->05",
+            "     0 202: a300 i := 0x300               i 000->300",
+            "     0 204: 6101 v1 := 0x01               v1 00->01",
+            "     0 206: f115 delay := v1              dt 00->01",
+            "     0 208: f107 v1 := delay             ",
+            "     0 20a: 1208 jump 0x208               pc ->208",
+            "     0 208: f107 v1 := delay             ",
+        ]
+    );
+    // the timer counted down between the frames
+    let lines = trace("all-frames", &PROGRAM, 2, TraceFilter::default(), None);
+    assert!(lines.contains(&"     1 208: f107 v1 := delay              v1 01->00".to_string()));
+}
+
+#[test]
+fn address_filter() {
+    let filter = TraceFilter {
+        addresses: parse_range("202-204", 16),
+        ..Default::default()
+    };
+    let lines = trace("addresses", &PROGRAM, 3, filter, None);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("     0 202: a300"));
+    assert!(lines[1].starts_with("     0 204: 6101"));
+}
+
+#[test]
+fn class_filter() {
+    let filter = TraceFilter {
+        classes: parse_classes("6,a"),
+        ..Default::default()
+    };
+    let lines = trace("classes", &PROGRAM, 3, filter, None);
+    let addresses: Vec<&str> = lines.iter().map(|line| &line[7..10]).collect();
+    assert_eq!(addresses, ["200", "202", "204"]);
+    assert_eq!(parse_classes("8,g"), None);
+    assert_eq!(parse_classes("10"), None);
+}
+
+#[test]
+fn frame_filter() {
+    let filter = TraceFilter {
+        frames: parse_range("2-3", 10),
+        ..Default::default()
+    };
+    let lines = trace("frames", &PROGRAM, 5, filter, None);
+    assert!(!lines.is_empty());
+    assert!(lines
+        .iter()
+        .all(|line| line.starts_with("     2 ") || line.starts_with("     3 ")));
+    assert!(lines.iter().any(|line| line.starts_with("     3 ")));
+    assert_eq!(parse_range("7", 10), Some(7..=7));
+    assert_eq!(parse_range("0x10-0x1f", 16), Some(16..=31));
+    assert_eq!(parse_range("a-", 16), None);
+}
+
+#[test]
+fn ring_is_written_on_error() {
+    // runs into a return with nothing on the stack
+    let program = [0x6001, 0x7001, 0x7001, 0x7001, 0x00EE];
+    let lines = trace("ring-error", &program, 5, TraceFilter::default(), Some(2));
+    assert_eq!(
+        lines,
+        [
+            "     0 206: 7001 v0 += 0x01               v0 03->04",
+            "     0 208: 00ee return                  ",
+            "error: return with an empty stack at 208",
+        ]
+    );
+
+    // without an error nothing is written
+    let lines = trace("ring-ok", &PROGRAM, 3, TraceFilter::default(), Some(2));
+    assert!(lines.is_empty(), "{lines:?}");
+}
+
+#[test]
+fn errors_are_written_without_a_ring() {
+    let lines = trace("error", &[0x0123], 1, TraceFilter::default(), None);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("     0 200: 0123 ???"));
+    assert!(lines[1].starts_with("error: "), "{}", lines[1]);
+}
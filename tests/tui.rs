@@ -0,0 +1,153 @@
+//! The terminal frontend's drawing and keys.
+
+use std::time::Instant;
+
+use chip8::debugger::Debugger;
+use chip8::scheduler::{Scheduler, Speed};
+use chip8::tui::{self, Controls, HeldKeys, KeyResult, HOLD_FRAMES};
+use chip8::{Interpreter, KeypadKey, Palette, TARGET_FPS, WIDTH};
+
+// the held keys line of the panel for the row with 4, 5, 6 and D
+fn keys_row(interpreter: &Interpreter) -> String {
+    tui::panel(interpreter)[8].clone()
+}
+
+#[test]
+fn half_blocks() {
+    let mut interpreter = Interpreter::new();
+    interpreter.screen[0][0] = true;
+    interpreter.screen[1][1] = true;
+    interpreter.screen[2][2] = true;
+    interpreter.screen[3][2] = true;
+    let lines = tui::half_blocks(&interpreter.screen);
+    assert_eq!(lines.len(), tui::ROWS);
+    assert!(lines.iter().all(|line| line.chars().count() == WIDTH));
+    assert!(lines[0].starts_with("▀▄ "));
+    assert!(lines[1].starts_with("  █ "));
+    assert!(lines[2].trim().is_empty());
+}
+
+#[test]
+fn colored_screen() {
+    let mut interpreter = Interpreter::new();
+    interpreter.palette = Palette::GREEN;
+    interpreter.screen[0][0] = true;
+    let mut out = Vec::new();
+    tui::draw_screen(&mut out, &interpreter, 1, 1).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.matches('▀').count(), WIDTH * tui::ROWS);
+    // the lit pixel in the foreground, then the background on itself
+    assert!(text.contains("\x1b[38;2;51;255;102m"));
+    assert!(text.contains("\x1b[38;2;10;20;10m\x1b[48;2;10;20;10m"));
+}
+
+#[test]
+fn panel() {
+    let mut interpreter = Interpreter::with_program(&[0x00E0, 0x6A12]);
+    interpreter.registers[0xA] = 0x12;
+    interpreter.stack.push(0x234);
+    let lines = tui::panel(&interpreter);
+    assert_eq!(lines[2], "V8 00  V9 00  VA 12  VB 00");
+    assert!(lines[4].starts_with("I 000  PC 200"));
+    assert_eq!(lines[6], "STACK 234");
+    assert_eq!(lines[7], "KEYS  · · · ·");
+    assert!(lines[12].starts_with(">200 00E0"));
+    assert!(lines[13].starts_with(" 202 6A12"));
+}
+
+#[test]
+fn keypad_layout() {
+    assert!(matches!(tui::keypad_key('1'), Some(KeypadKey::Key1)));
+    assert!(matches!(tui::keypad_key('x'), Some(KeypadKey::Key0)));
+    assert!(matches!(tui::keypad_key('V'), Some(KeypadKey::KeyF)));
+    assert!(tui::keypad_key('p').is_none());
+}
+
+#[test]
+fn keys_are_released_after_a_while() {
+    let mut interpreter = Interpreter::new();
+    let mut held = HeldKeys::new(false);
+    held.press(&mut interpreter, KeypadKey::Key5);
+    assert_eq!(keys_row(&interpreter), "      · 5 · ·");
+    for _ in 1..HOLD_FRAMES {
+        held.end_frame(&mut interpreter);
+    }
+    // a repeat keeps it down
+    held.press(&mut interpreter, KeypadKey::Key5);
+    for _ in 1..HOLD_FRAMES {
+        held.end_frame(&mut interpreter);
+    }
+    assert_eq!(keys_row(&interpreter), "      · 5 · ·");
+    held.end_frame(&mut interpreter);
+    assert_eq!(keys_row(&interpreter), "      · · · ·");
+}
+
+#[test]
+fn keys_wait_for_release_when_reported() {
+    let mut interpreter = Interpreter::new();
+    let mut held = HeldKeys::new(true);
+    held.press(&mut interpreter, KeypadKey::KeyD);
+    for _ in 0..1000 {
+        held.end_frame(&mut interpreter);
+    }
+    assert_eq!(keys_row(&interpreter), "      · · · D");
+    held.release(&mut interpreter, KeypadKey::KeyD);
+    assert_eq!(keys_row(&interpreter), "      · · · ·");
+}
+
+fn press(
+    controls: &mut Controls,
+    interpreter: &mut Interpreter,
+    scheduler: &mut Scheduler,
+    n: u8,
+) -> KeyResult {
+    controls.function_key(n, interpreter, scheduler, "test.ch8")
+}
+
+#[test]
+fn function_keys() {
+    let mut interpreter = Interpreter::with_program(&[0x6001, 0x1202]);
+    let mut scheduler = Scheduler::new(TARGET_FPS, Instant::now());
+    let mut controls = Controls::new(Debugger::new(), false);
+    let redraw = KeyResult {
+        redraw: true,
+        ..Default::default()
+    };
+
+    // showing the panel and hiding it again, which has to clear what it drew
+    assert_eq!(
+        press(&mut controls, &mut interpreter, &mut scheduler, 1),
+        redraw
+    );
+    assert!(controls.show_panel);
+    assert_eq!(
+        press(&mut controls, &mut interpreter, &mut scheduler, 1),
+        redraw
+    );
+    assert!(!controls.show_panel);
+
+    // a frame at a time only while paused
+    assert_eq!(
+        press(&mut controls, &mut interpreter, &mut scheduler, 3),
+        KeyResult::default()
+    );
+    assert_eq!(
+        press(&mut controls, &mut interpreter, &mut scheduler, 2),
+        KeyResult::default()
+    );
+    assert!(controls.debugger.is_paused());
+    assert!(press(&mut controls, &mut interpreter, &mut scheduler, 3).advance);
+
+    // an instruction at a time
+    assert_eq!(
+        press(&mut controls, &mut interpreter, &mut scheduler, 10),
+        KeyResult::default()
+    );
+    assert_eq!(interpreter.registers[0], 1);
+    assert_eq!(interpreter.program_counter, 0x202);
+
+    press(&mut controls, &mut interpreter, &mut scheduler, 6);
+    assert_eq!(scheduler.speed(), Speed::Normal.faster());
+    press(&mut controls, &mut interpreter, &mut scheduler, 4);
+    assert_eq!(scheduler.speed(), Speed::Normal);
+}
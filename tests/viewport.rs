@@ -0,0 +1,57 @@
+//! Scaling the screen up to the window.
+
+use chip8::viewport::{scale_nearest, scaled_size, Scaling};
+
+#[test]
+fn parse() {
+    assert_eq!(Scaling::default(), Scaling::Integer);
+    assert_eq!(Scaling::parse("integer"), Some(Scaling::Integer));
+    assert_eq!(Scaling::parse("fit"), Some(Scaling::Fit));
+    assert_eq!(Scaling::parse("stretch"), None);
+}
+
+#[test]
+fn integer_scaling() {
+    let integer = |window| scaled_size((64, 32), window, Scaling::Integer);
+    assert_eq!(integer((640, 320)), (640, 320));
+    // the narrower side decides, the rest is bars
+    assert_eq!(integer((700, 500)), (640, 320));
+    assert_eq!(integer((1920, 1080)), (1920, 960));
+    assert_eq!(integer((127, 1000)), (64, 32));
+}
+
+#[test]
+fn fit_scaling() {
+    let fit = |window| scaled_size((64, 32), window, Scaling::Fit);
+    assert_eq!(fit((640, 320)), (640, 320));
+    assert_eq!(fit((700, 500)), (700, 350));
+    assert_eq!(fit((700, 300)), (600, 300));
+    assert_eq!(fit((1920, 1080)), (1920, 960));
+}
+
+#[test]
+fn never_smaller_than_frame() {
+    for scaling in [Scaling::Integer, Scaling::Fit] {
+        assert_eq!(scaled_size((64, 32), (0, 0), scaling), (64, 32));
+        assert_eq!(scaled_size((64, 32), (30, 300), scaling), (64, 32));
+    }
+}
+
+#[test]
+fn nearest_neighbour() {
+    // 2x1 red and blue
+    let frame = [255, 0, 0, 255, 0, 0, 255, 255];
+    let mut out = vec![0; 4 * 2 * 4];
+    scale_nearest(&frame, (2, 1), &mut out, (4, 2));
+    for row in out.chunks_exact(16) {
+        assert_eq!(row[..8], [255, 0, 0, 255, 255, 0, 0, 255]);
+        assert_eq!(row[8..], [0, 0, 255, 255, 0, 0, 255, 255]);
+    }
+
+    // uneven: three columns from two
+    let mut out = vec![0; 3 * 4];
+    scale_nearest(&frame, (2, 1), &mut out, (3, 1));
+    assert_eq!(out[..4], [255, 0, 0, 255]);
+    assert_eq!(out[4..8], [255, 0, 0, 255]);
+    assert_eq!(out[8..], [0, 0, 255, 255]);
+}